@@ -0,0 +1,163 @@
+//! Derives for `onemut`.
+//!
+//! This crate is re-exported by `onemut`, and shouldn't be used directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Path};
+
+/// Implements `onemut::Overlaid` for a struct, and defines it's overlay
+/// type, which gets the `Overlay` suffix, and it's dirty fields type,
+/// which gets the `Dirty` suffix.
+///
+/// For each field `x`, the overlay gets `x()` for shared access,
+/// and `x_mut()` for mut access, which clones the field on it's first call.
+/// `is_written()` indicates whether any field got mutably accessed.
+/// A field whose name collides with one of those methods is rejected.
+///
+/// `#[onemut(crate = path)]` sets the path to the `onemut` crate, for
+/// when it's renamed or re-exported, and defaults to `::onemut`.
+#[proc_macro_derive(Overlaid, attributes(onemut))]
+pub fn derive_overlaid(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match overlaid(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn overlaid(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "generic structs are not supported",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "only structs with named fields are supported",
+                ))
+            }
+        },
+        _ => return Err(Error::new(Span::call_site(), "only structs are supported")),
+    };
+
+    let krate = crate_path(&input)?;
+    check_collisions(fields.iter().map(|f| f.ident.as_ref().unwrap()))?;
+
+    let dirty = format_ident!("{}Dirty", name);
+    let overlay = format_ident!("{}Overlay", name);
+    let field: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let field_mut: Vec<_> = field.iter().map(|f| format_ident!("{}_mut", f)).collect();
+    let field_vis: Vec<_> = fields.iter().map(|f| &f.vis).collect();
+    let ty: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+
+    Ok(quote! {
+        /// The fields that got mutably accessed.
+        #[derive(Default)]
+        #vis struct #dirty {
+            #( #field: ::std::option::Option<#ty>, )*
+        }
+
+        /// Field-wise clone-on-write access.
+        #vis struct #overlay<'a> {
+            original: &'a #name,
+            dirty: #dirty,
+        }
+
+        #[allow(dead_code)]
+        impl<'a> #overlay<'a> {
+            #(
+                #field_vis fn #field(&self) -> &#ty {
+                    match &self.dirty.#field {
+                        ::std::option::Option::Some(copy) => copy,
+                        ::std::option::Option::None => &self.original.#field,
+                    }
+                }
+
+                #field_vis fn #field_mut(&mut self) -> &mut #ty {
+                    let original = self.original;
+                    self.dirty
+                        .#field
+                        .get_or_insert_with(|| ::std::clone::Clone::clone(&original.#field))
+                }
+            )*
+
+            /// Whether any field got mutably accessed, ie. whether
+            /// any field got cloned.
+            pub fn is_written(&self) -> bool {
+                #( self.dirty.#field.is_some() || )* false
+            }
+        }
+
+        impl #krate::overlay::Overlaid for #name {
+            type Dirty = #dirty;
+            type Overlay<'a> = #overlay<'a>;
+
+            fn overlay(&self, dirty: Self::Dirty) -> Self::Overlay<'_> {
+                #overlay {
+                    original: self,
+                    dirty,
+                }
+            }
+
+            fn into_dirty(overlay: Self::Overlay<'_>) -> Self::Dirty {
+                overlay.dirty
+            }
+
+            fn write_back(&mut self, dirty: Self::Dirty) {
+                #(
+                    if let ::std::option::Option::Some(copy) = dirty.#field {
+                        self.#field = copy;
+                    }
+                )*
+            }
+        }
+    })
+}
+
+/// Reads `#[onemut(crate = path)]`.
+fn crate_path(input: &DeriveInput) -> Result<Path, Error> {
+    let mut krate = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("onemut")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                krate = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `crate = path`"))
+            }
+        })?;
+    }
+    Ok(krate.unwrap_or_else(|| syn::parse_quote!(::onemut)))
+}
+
+/// Rejects the fields that have the same name as a generated method,
+/// ie. `x_mut` next to `x`, or `is_written`.
+fn check_collisions<'a>(fields: impl Iterator<Item = &'a syn::Ident> + Clone) -> Result<(), Error> {
+    let methods: HashSet<String> = fields
+        .clone()
+        .map(|f| format!("{}_mut", f))
+        .chain(Some("is_written".to_string()))
+        .collect();
+    for field in fields {
+        if methods.contains(&field.to_string()) {
+            return Err(Error::new_spanned(
+                field,
+                format!(
+                    "the field `{}` collides with a method of the overlay",
+                    field
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
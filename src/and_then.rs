@@ -0,0 +1,329 @@
+use super::{target, Apply, ConsumedToken, OneMut, PartialApply, Prepared, Take, TakeOwned, Token};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// Information to diverge what the later closure of an `AndThen` receives.
+pub mod link {
+    /// The later closure receives the output of the previous members.
+    pub struct Output;
+
+    /// The later closure receives a `Pending` view into the modified
+    /// copies of the previous members.
+    pub struct Pending;
+}
+
+/// Read-only access into the modified copies of the previous members
+/// of an `AndThen`, before any of them is committed.
+///
+/// Only the previous members are accessible, as the later members were
+/// not yet modified. The view is a `&T` for a single member, and for
+/// nested `AndThen`s it's nested tuples of `&T`s, in the same order as
+/// the members. For a `cow` member it's the `&T` behind the `Arc<T>`,
+/// and for an `stm` member `T` is the tuple of the `TVar`s values.
+///
+/// An `overlay` member has no view, see `PartialPending`.
+///
+/// See `PartialPending`.
+#[derive(Clone, Copy)]
+pub struct Pending<V> {
+    view: V,
+}
+
+impl<V: Copy> Pending<V> {
+    pub fn get(&self) -> V {
+        self.view
+    }
+}
+
+impl<V> Deref for Pending<V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.view
+    }
+}
+
+/// Resolves the modified copy of `T` out of it's `PartialApply::Next`,
+/// so that later members of an `AndThen` can read it.
+///
+/// This is not implemented for `prepared::mode::Overlay`, whose copy
+/// only holds the mutably accessed fields.
+///
+/// `Outlives` is never set, it only bounds `'a` by `Self`, so that
+/// `for<'a> PartialPending<'a, ..>` doesn't require `Self: 'static`.
+pub trait PartialPending<'a, T, F, O, E, Outlives = &'a Self>: PartialApply<T, F, O, E> {
+    /// A `&'a T`, or nested tuples of them.
+    type View;
+
+    /// Views the copy of `T` in `next`, or the original `T` if there is
+    /// no copy yet.
+    fn pending(&'a self, next: &'a Self::Next) -> Self::View;
+}
+
+/// A modification into `T2` that depends on the output of a previous
+/// modification `A1`.
+///
+/// During `apply`, the copy of `A1` is modified first, and then the
+/// copy of `T2` is modified by a closure that also receives the output
+/// of `A1`. Only after both modifications were successful, the
+/// original values are replaced, so both are still committed together.
+///
+/// The output is `(O1, O2)`. As `AndThen` can itself be followed by
+/// another `and_then()`, later closures receive the outputs of all of
+/// the previous members.
+///
+/// The later member `B` is only applied for a `OneMut` in the default
+/// mode, as it's copy is written back as is, which would otherwise skip
+/// the checks of it's mode.
+///
+/// `K` indicates what the later closure receives, see `link`.
+pub struct AndThen<A1, B, T2, F1, G, K = link::Output> {
+    a1: A1,
+    b: B,
+    fs: (F1, G),
+    _t: PhantomData<T2>,
+    _link: PhantomData<K>,
+}
+
+impl<A1, B, T2, F1, G, K> AndThen<A1, B, T2, F1, G, K> {
+    pub fn new(a1: A1, b: B, g: G) -> Self
+    where
+        A1: Take<F1, target::Function>,
+        F1: Clone,
+    {
+        let f1: &F1 = a1.take_ref();
+        let f1 = f1.clone();
+        Self {
+            a1,
+            b,
+            fs: (f1, g),
+            _t: PhantomData,
+            _link: PhantomData,
+        }
+    }
+
+    /// Adds another modification, into `T3`, whose closure receives the
+    /// outputs of this one.
+    #[allow(clippy::type_complexity)]
+    pub fn and_then<'t3, T3, G3>(
+        self,
+        c: OneMut<'t3, T3>,
+        g3: G3,
+    ) -> AndThen<Self, OneMut<'t3, T3>, T3, (F1, G), G3>
+    where
+        F1: Clone,
+        G: Clone,
+    {
+        AndThen::new(self, c, g3)
+    }
+
+    /// Adds another modification, into `T3`, whose closure can read the
+    /// modified copies of this one.
+    #[allow(clippy::type_complexity)]
+    pub fn and_then_pending<'t3, T3, G3>(
+        self,
+        c: OneMut<'t3, T3>,
+        g3: G3,
+    ) -> AndThen<Self, OneMut<'t3, T3>, T3, (F1, G), G3, link::Pending>
+    where
+        F1: Clone,
+        G: Clone,
+    {
+        AndThen::new(self, c, g3)
+    }
+}
+
+impl<OuterT, T, F, E, M> Prepared<OuterT, T, F, E, M> {
+    /// Adds a modification into `T2`, whose closure receives the output
+    /// of this one, so that it may depend on it.
+    ///
+    /// `g` receives `&O1` and `&mut T2`. Both modifications are
+    /// committed together.
+    ///
+    /// `b` must be in the default mode, as it's copy is written back as
+    /// is, see `OneMut::with_invariant()`.
+    pub fn and_then<'t2, T2, G>(
+        self,
+        b: OneMut<'t2, T2>,
+        g: G,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, F, G>
+    where
+        F: Clone,
+    {
+        AndThen::new(self, b, g)
+    }
+
+    /// Adds a modification into `T2`, whose closure can read the modified
+    /// copy of this one, before it's committed.
+    ///
+    /// `g` receives a `Pending` view into the copy and `&mut T2`. Both
+    /// modifications are committed together.
+    ///
+    /// `b` must be in the default mode, as it's copy is written back as
+    /// is, see `OneMut::with_invariant()`.
+    #[allow(clippy::type_complexity)]
+    pub fn and_then_pending<'t2, T2, G>(
+        self,
+        b: OneMut<'t2, T2>,
+        g: G,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, F, G, link::Pending>
+    where
+        F: Clone,
+    {
+        AndThen::new(self, b, g)
+    }
+}
+
+impl<A1, B, T2, F1, G, K> Take<(F1, G), target::Function> for AndThen<A1, B, T2, F1, G, K> {
+    fn take_ref(&self) -> &(F1, G) {
+        &self.fs
+    }
+
+    fn take_mut(&mut self) -> &mut (F1, G) {
+        &mut self.fs
+    }
+}
+
+impl<'t1, 't2, 'tboth, A1, B, T1, T2, F1, G, K> TakeOwned<Token<'tboth, (T1, T2)>, target::Token>
+    for AndThen<A1, B, T2, F1, G, K>
+where
+    A1: TakeOwned<Token<'t1, T1>, target::Token>,
+    B: TakeOwned<Token<'t2, T2>, target::Token>,
+    T1: 't1,
+    T2: 't2,
+{
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'tboth, (T1, T2)> {
+        let t1 = self.a1.take_owned();
+        let t2 = self.b.take_owned();
+        t1.then(t2)
+    }
+}
+
+impl<'t2, A1, T1, T2, F1, G, O1, O2, E> PartialApply<(T1, T2), (F1, G), (O1, O2), E>
+    for AndThen<A1, OneMut<'t2, T2>, T2, F1, G, link::Output>
+where
+    A1: PartialApply<T1, F1, O1, E>,
+    G: FnOnce(&O1, &mut T2) -> Result<O2, E>,
+    T2: Clone,
+{
+    type Next = (A1::Next, T2);
+
+    fn get_next(&self) -> Self::Next {
+        let next1 = A1::get_next(&self.a1);
+        let next2: &T2 = self.b.take_ref();
+        (next1, next2.clone())
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn modify_next(
+        &self,
+        (next1, mut next2): Self::Next,
+        (f1, g): (F1, G),
+    ) -> Result<((O1, O2), Self::Next), E> {
+        let (o1, next1) = A1::modify_next(&self.a1, next1, f1)?;
+        let o2 = (g)(&o1, &mut next2)?;
+        Ok(((o1, o2), (next1, next2)))
+    }
+
+    fn replace(&mut self, (next1, next2): Self::Next) {
+        A1::replace(&mut self.a1, next1);
+        let current: &mut T2 = self.b.take_mut();
+        *current = next2;
+    }
+}
+
+impl<'t2, A1, T1, T2, F1, G, O1, O2, E> PartialApply<(T1, T2), (F1, G), (O1, O2), E>
+    for AndThen<A1, OneMut<'t2, T2>, T2, F1, G, link::Pending>
+where
+    A1: for<'a> PartialPending<'a, T1, F1, O1, E>,
+    G: for<'a> FnOnce(
+        Pending<<A1 as PartialPending<'a, T1, F1, O1, E>>::View>,
+        &mut T2,
+    ) -> Result<O2, E>,
+    T2: Clone,
+{
+    type Next = (A1::Next, T2);
+
+    fn get_next(&self) -> Self::Next {
+        let next1 = A1::get_next(&self.a1);
+        let next2: &T2 = self.b.take_ref();
+        (next1, next2.clone())
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn modify_next(
+        &self,
+        (next1, mut next2): Self::Next,
+        (f1, g): (F1, G),
+    ) -> Result<((O1, O2), Self::Next), E> {
+        let (o1, next1) = A1::modify_next(&self.a1, next1, f1)?;
+        let view = self.a1.pending(&next1);
+        let o2 = (g)(Pending { view }, &mut next2)?;
+        Ok(((o1, o2), (next1, next2)))
+    }
+
+    fn replace(&mut self, (next1, next2): Self::Next) {
+        A1::replace(&mut self.a1, next1);
+        let current: &mut T2 = self.b.take_mut();
+        *current = next2;
+    }
+}
+
+impl<'a, A1, B, T1, T2, F1, G, K, O1, O2, E> PartialPending<'a, (T1, T2), (F1, G), (O1, O2), E>
+    for AndThen<A1, B, T2, F1, G, K>
+where
+    Self: PartialApply<(T1, T2), (F1, G), (O1, O2), E, Next = (A1::Next, T2)>,
+    A1: PartialPending<'a, T1, F1, O1, E>,
+{
+    type View = (A1::View, &'a T2);
+
+    fn pending(&'a self, (next1, next2): &'a Self::Next) -> Self::View {
+        (self.a1.pending(next1), next2)
+    }
+}
+
+unsafe impl<'t1, 't2, 'tboth, A1, B, T1, T2, F1, G, K, O, E> Apply<'tboth, (T1, T2), (F1, G), O, E>
+    for AndThen<A1, B, T2, F1, G, K>
+where
+    Self: PartialApply<(T1, T2), (F1, G), O, E>,
+    A1: TakeOwned<Token<'t1, T1>, target::Token>,
+    B: TakeOwned<Token<'t2, T2>, target::Token>,
+    T1: 't1,
+    T2: 't2,
+    F1: Clone,
+    G: Clone,
+{
+    fn apply(mut self) -> crate::AllOrNone<'tboth, O, E, (T1, T2)> {
+        let next = Self::get_next(&self);
+        let fs = self.fs.clone();
+
+        // modify both copies, in order
+        let (o, next) = match self.modify_next(next, fs) {
+            Ok(v) => v,
+            Err(e) => {
+                // Safety:
+                //
+                // this is indicating that the mutation failed,
+                // and also preventing further mutations
+                let t: Token<(T1, T2)> = unsafe { self.take_owned() };
+                return Err((e, t));
+            }
+        };
+
+        // Safety:
+        //
+        // only replace after both modifications were successfull
+        // and after this, an `Ok` return is guaranteed
+        Self::replace(&mut self, next);
+
+        // Safety:
+        //
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        let t: Token<(T1, T2)> = unsafe { self.take_owned() };
+        Ok((o, ConsumedToken::from(t)))
+    }
+}
@@ -0,0 +1,221 @@
+use crate::versioned::{Conflict, PartialOptimistic};
+use crate::Chain;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A shared `T`, read as `Arc<T>` snapshots and modified by
+/// compare-and-swap of the `Arc` pointer.
+///
+/// Readers never block: `load()` only increments the reference count
+/// of the current `Arc`. A modification runs on a copy of a snapshot,
+/// and the commit swaps the pointer only if it still points into the
+/// snapshot, otherwise the modification is retried from a new snapshot.
+///
+/// When chained with other cells, the commit first claims all of the
+/// cells and checks that all of them are unchanged, and only then swaps
+/// the pointers, so either all of the pointers are swapped or none of
+/// them are. Readers of each cell still see each swap on it's own, so
+/// `load()`ing several cells is not a consistent snapshot across them.
+///
+/// See `AtomicShared::prepare()`.
+pub struct AtomicShared<T> {
+    /// Created from `Arc::into_raw`.
+    ptr: AtomicPtr<T>,
+    /// How many readers may be about to increment the reference count
+    /// of a pointer they've loaded, for each parity of `epoch`.
+    readers: [AtomicUsize; 2],
+    /// Incremented by each swap, so that a writer only waits for the
+    /// readers that started before it's swap.
+    epoch: AtomicUsize,
+    /// Claimed by writers, during a commit.
+    writer: AtomicBool,
+    _arc: PhantomData<Arc<T>>,
+}
+
+impl<T> AtomicShared<T> {
+    pub fn new(value: T) -> Self {
+        Self::from_arc(Arc::new(value))
+    }
+
+    pub fn from_arc(arc: Arc<T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Arc::into_raw(arc) as *mut T),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            epoch: AtomicUsize::new(0),
+            writer: AtomicBool::new(false),
+            _arc: PhantomData,
+        }
+    }
+
+    /// Gets the current `T`, without blocking.
+    pub fn load(&self) -> Arc<T> {
+        let readers = loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let readers = &self.readers[epoch % 2];
+            readers.fetch_add(1, Ordering::SeqCst);
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                break readers;
+            }
+            // a swap started in between, so registers into the new epoch
+            readers.fetch_sub(1, Ordering::SeqCst);
+        };
+        let ptr = self.ptr.load(Ordering::SeqCst);
+        // Safety:
+        //
+        // while the `readers` of this epoch are non-zero, writers don't
+        // release the pointers they've swapped out, so `ptr` is still alive
+        let arc = unsafe {
+            Arc::increment_strong_count(ptr);
+            Arc::from_raw(ptr)
+        };
+        readers.fetch_sub(1, Ordering::SeqCst);
+        arc
+    }
+
+    pub fn into_arc(self) -> Arc<T> {
+        let ptr = self.ptr.load(Ordering::SeqCst);
+        std::mem::forget(self);
+        // Safety:
+        //
+        // the pointer came from `Arc::into_raw`, and it's ownership
+        // is moved out of the (forgotten) cell
+        unsafe { Arc::from_raw(ptr) }
+    }
+
+    /// Defines how `T` should be mutated, given an `Ok` response.
+    ///
+    /// See `OneMut::prepare()` for the `Result` signaling.
+    /// The closure may be executed more than once, as each conflicting
+    /// commit makes the transaction be retried, so it should not have
+    /// side effects besides the mutation into `T`.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare<F, E>(&self, f: F) -> AtomicPrepared<'_, T, F, E> {
+        self.unchecked_prepare(f)
+    }
+
+    /// See `AtomicShared::prepare()`.
+    pub fn unchecked_prepare<F, E>(&self, f: F) -> AtomicPrepared<'_, T, F, E> {
+        AtomicPrepared {
+            inner: self,
+            f,
+            _err: PhantomData,
+        }
+    }
+
+    /// Swaps the pointer, and releases the old `Arc` once no reader
+    /// can be about to use it.
+    ///
+    /// Must only be called by the writer that claimed the cell.
+    fn swap(&self, next: Arc<T>) {
+        let old = self
+            .ptr
+            .swap(Arc::into_raw(next) as *mut T, Ordering::SeqCst);
+        // only waits for the readers of the previous epoch, which may
+        // have loaded the old pointer. The readers of the new epoch can
+        // only see the new pointer, so they can't stall the swap
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+        while self.readers[epoch % 2].load(Ordering::SeqCst) != 0 {
+            std::thread::yield_now();
+        }
+        // Safety:
+        //
+        // the pointer came from `Arc::into_raw`, and no reader is
+        // about to increment it's reference count
+        drop(unsafe { Arc::from_raw(old) });
+    }
+}
+
+impl<T> Drop for AtomicShared<T> {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        // Safety:
+        //
+        // the pointer came from `Arc::into_raw`, and there can be no
+        // readers during the drop
+        drop(unsafe { Arc::from_raw(ptr) });
+    }
+}
+
+/// Holds a single scoped modification into a copy of a snapshot of an
+/// `AtomicShared<T>`.
+///
+/// See `Optimistic::transact()`.
+pub struct AtomicPrepared<'t, T, F, E> {
+    inner: &'t AtomicShared<T>,
+    f: F,
+    _err: PhantomData<E>,
+}
+
+impl<'t, T, F, E> AtomicPrepared<'t, T, F, E> {
+    /// Chains this modification with another one, so that both are
+    /// committed in the same transaction.
+    pub fn chain<A2>(self, a2: A2) -> Chain<Self, A2> {
+        Chain::new(self, a2)
+    }
+}
+
+/// A claim into an `AtomicShared<T>`, held during a commit.
+pub struct AtomicGuard<'t, T> {
+    inner: &'t AtomicShared<T>,
+}
+
+impl<'t, T> Drop for AtomicGuard<'t, T> {
+    fn drop(&mut self) {
+        self.inner.writer.store(false, Ordering::SeqCst);
+    }
+}
+
+unsafe impl<'t, T, F, O, E> PartialOptimistic<T, F, O, E> for AtomicPrepared<'t, T, F, E>
+where
+    F: FnOnce(&mut T) -> Result<O, E> + Clone,
+    T: Clone,
+{
+    /// The snapshot itself, which also prevents it's pointer from
+    /// being reused by other values.
+    type Stamp = Arc<T>;
+    type Guard = AtomicGuard<'t, T>;
+
+    fn snapshot(&self) -> (Arc<T>, T) {
+        let arc = self.inner.load();
+        let next = T::clone(&arc);
+        (arc, next)
+    }
+
+    fn next_fn(&self) -> F {
+        self.f.clone()
+    }
+
+    fn modify_next(mut next: T, f: F) -> Result<(O, T), E> {
+        let o = (f)(&mut next)?;
+        Ok((o, next))
+    }
+
+    fn try_lock_unchanged(&self, stamp: &Arc<T>) -> Result<Self::Guard, Conflict> {
+        let claimed = self
+            .inner
+            .writer
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok();
+        if !claimed {
+            return Err(Conflict::Busy);
+        }
+        let guard = AtomicGuard { inner: self.inner };
+        if std::ptr::eq(self.inner.ptr.load(Ordering::SeqCst), Arc::as_ptr(stamp)) {
+            Ok(guard)
+        } else {
+            Err(Conflict::Changed)
+        }
+    }
+
+    fn replace(guard: &mut Self::Guard, next: T) {
+        guard.inner.swap(Arc::new(next));
+    }
+
+    fn cells(&self, cells: &mut Vec<usize>) {
+        cells.push(self.inner as *const AtomicShared<T> as usize);
+    }
+}
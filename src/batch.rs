@@ -0,0 +1,76 @@
+use crate::{AllOrNone, ConsumedToken, OneMut, Take, Token};
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+/// Modifies all of the `T`s of a runtime-sized batch, all-or-nothing.
+///
+/// `f` receives the index of the item and a copy of it's `T`. Only after
+/// all of the copies were successfully modified, the original values
+/// are replaced. Otherwise, none of them are.
+///
+/// The tokens of the whole batch are merged into a single one, for `[T]`.
+/// For fixed-size batches, see `batch_array()`.
+pub fn batch<'t, I, T, F, O, E>(ones: I, mut f: F) -> AllOrNone<'t, Vec<O>, E, [T]>
+where
+    I: IntoIterator<Item = OneMut<'t, T>>,
+    F: FnMut(usize, &mut T) -> Result<O, E>,
+    T: 't + Clone,
+{
+    let mut ones: Vec<OneMut<'t, T>> = ones.into_iter().collect();
+
+    // modify all copies, in order
+    let mut outputs = Vec::with_capacity(ones.len());
+    let mut nexts = Vec::with_capacity(ones.len());
+    for (i, one) in ones.iter().enumerate() {
+        let mut next = one.as_ref().clone();
+        match (f)(i, &mut next) {
+            Ok(o) => outputs.push(o),
+            Err(e) => {
+                // this is indicating that the mutation failed,
+                // and also preventing further mutations
+                return Err((e, merge(ones)));
+            }
+        }
+        nexts.push(next);
+    }
+
+    // only replace after all modifications were successful
+    for (one, next) in ones.iter_mut().zip(nexts) {
+        let current: &mut T = one.take_mut();
+        *current = next;
+    }
+
+    // this is indicating that the mutation was successful,
+    // and also preventing further mutations
+    Ok((outputs, ConsumedToken::from(merge(ones))))
+}
+
+/// Same as `batch()`, but for a fixed-size batch, whose tokens are
+/// merged into a single one for `[T; N]`.
+pub fn batch_array<'t, const N: usize, T, F, O, E>(
+    ones: [OneMut<'t, T>; N],
+    f: F,
+) -> AllOrNone<'t, [O; N], E, [T; N]>
+where
+    F: FnMut(usize, &mut T) -> Result<O, E>,
+    T: 't + Clone,
+{
+    match batch(ones, f) {
+        Ok((outputs, _tokens)) => {
+            let outputs: [O; N] = match outputs.try_into() {
+                Ok(outputs) => outputs,
+                Err(_) => unreachable!("the batch has exactly N outputs"),
+            };
+            Ok((outputs, ConsumedToken::from(Token(PhantomData))))
+        }
+        Err((e, _tokens)) => Err((e, Token(PhantomData))),
+    }
+}
+
+/// Merges the tokens of all of the items.
+fn merge<'t, T>(ones: Vec<OneMut<'t, T>>) -> Token<'t, [T]> {
+    for one in ones {
+        let _token: Token<'t, T> = one.unchecked_token();
+    }
+    Token(PhantomData)
+}
@@ -0,0 +1,79 @@
+use paste::paste;
+use std::cell::{RefCell, RefMut};
+
+/// Indicates that some `RefCell` was already borrowed.
+///
+/// `index` is the tuple position of the first `RefCell` that
+/// could not be mutably borrowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorrowAllMutError {
+    pub index: usize,
+}
+
+/// Mutably borrows a tuple of `RefCell`s as a single operation.
+///
+/// Either all of the `RefCell`s get borrowed, or none of them do,
+/// and an error is returned instead of panicking.
+///
+/// The returned guards can be turned into `OneMut`s with
+/// `from_apply::OneMuts`, and all borrows are released together when
+/// the guards are dropped, after the appliance either succeeded or
+/// failed.
+///
+/// `Cell`s don't need this, as a `&mut Cell<T>` already gives a
+/// `&mut T` with `Cell::get_mut()`.
+pub trait BorrowAllMut<'a> {
+    type Guards;
+    fn borrow_all_mut(self) -> Result<Self::Guards, BorrowAllMutError>;
+}
+
+/// Mutably borrows a tuple of `RefCell`s as a single operation.
+///
+/// See `BorrowAllMut`.
+pub fn borrow_all_mut<'a, C: BorrowAllMut<'a>>(cells: C) -> Result<C::Guards, BorrowAllMutError> {
+    cells.borrow_all_mut()
+}
+
+macro_rules! borrow_all_mut_impls {
+    ( $( $n:tt ),+ ) => {
+        paste! {
+            impl<
+                'a,
+                $( [<T $n>]: 'a, )+
+            > BorrowAllMut<'a> for (
+                $( &'a RefCell<[<T $n>]>, )+
+            ) {
+                type Guards = (
+                    $( RefMut<'a, [<T $n>]>, )+
+                );
+
+                fn borrow_all_mut(self) -> Result<Self::Guards, BorrowAllMutError> {
+                    let (
+                        $( [<c $n>], )+
+                    ) = self;
+                    // on errors, the already borrowed cells are released
+                    Ok((
+                        $(
+                            [<c $n>]
+                                .try_borrow_mut()
+                                .map_err(|_| BorrowAllMutError { index: $n - 1 })?,
+                        )+
+                    ))
+                }
+            }
+        }
+    };
+}
+
+borrow_all_mut_impls! {1}
+borrow_all_mut_impls! {1, 2}
+borrow_all_mut_impls! {1, 2, 3}
+borrow_all_mut_impls! {1, 2, 3, 4}
+borrow_all_mut_impls! {1, 2, 3, 4, 5}
+borrow_all_mut_impls! {1, 2, 3, 4, 5, 6}
+borrow_all_mut_impls! {1, 2, 3, 4, 5, 6, 7}
+borrow_all_mut_impls! {1, 2, 3, 4, 5, 6, 7, 8}
+borrow_all_mut_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9}
+borrow_all_mut_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10}
+borrow_all_mut_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11}
+borrow_all_mut_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12}
@@ -1,4 +1,8 @@
-use super::{target, Apply, ConsumedToken, PartialApply, Take, TakeOwned, Token};
+use super::{
+    target, Apply, ApplyReversible, ApplyScratch, ConsumedToken, PartialApply, PartialPending,
+    PartialScratch, PartialSwap, Scratch, Take, TakeOwned, Token, Undo,
+};
+use std::marker::PhantomData;
 
 /// Container of `Prepared` items.
 ///
@@ -6,8 +10,8 @@ use super::{target, Apply, ConsumedToken, PartialApply, Take, TakeOwned, Token};
 /// and only after both modifications successfully were executed,
 /// the original `A1` and `A2` are replaced with the modified ones.
 pub struct Chain<A1, A2> {
-    a1: A1,
-    a2: A2,
+    pub(crate) a1: A1,
+    pub(crate) a2: A2,
 }
 
 impl<A1, A2> Chain<A1, A2> {
@@ -15,6 +19,32 @@ impl<A1, A2> Chain<A1, A2> {
         Self { a1, a2 }
     }
 
+    /// Adds a check over all of the modified copies, which runs after
+    /// both modifications were successful and before any original value
+    /// is replaced.
+    ///
+    /// If the check fails, the whole appliance fails, so invariants that
+    /// relate `T1` and `T2` can't be broken by the commit.
+    ///
+    /// The check receives the same view as a `Pending`, so that's `(&T1,
+    /// &T2)` for members in the default mode.
+    pub fn with_check<C, F1, F2>(self, check: C) -> CheckedChain<A1, A2, F1, F2, C>
+    where
+        A1: Take<F1, target::Function>,
+        A2: Take<F2, target::Function>,
+        F1: Clone,
+        F2: Clone,
+    {
+        let f1: &F1 = self.a1.take_ref();
+        let f2: &F2 = self.a2.take_ref();
+        let fs = (f1.clone(), f2.clone());
+        CheckedChain {
+            chain: self,
+            fs,
+            check,
+        }
+    }
+
     // TODO: need to test
     pub fn chain<A3, F3>(self, a3: A3) -> Chain<(A1, A2), A3> {
         Chain::new((self.a1, self.a2), a3)
@@ -22,7 +52,7 @@ impl<A1, A2> Chain<A1, A2> {
 }
 
 // TODO: check if is necessary
-impl<'t, A1, A2, F1, F2> TakeOwned<(F1, F2), target::Function> for Chain<A1, A2>
+impl<A1, A2, F1, F2> TakeOwned<(F1, F2), target::Function> for Chain<A1, A2>
 where
     A1: TakeOwned<F1, target::Function>,
     A2: TakeOwned<F2, target::Function>,
@@ -37,13 +67,33 @@ where
     }
 }
 
-impl<'t, A1, A2, T1, T2, F1, F2, O1, O2, E> PartialApply<(T1, T2), (F1, F2), (O1, O2), E>
+impl<'t1, 't2, 'tboth, A1, A2, T1, T2> TakeOwned<Token<'tboth, (T1, T2)>, target::Token>
+    for Chain<A1, A2>
+where
+    A1: TakeOwned<Token<'t1, T1>, target::Token>,
+    A2: TakeOwned<Token<'t2, T2>, target::Token>,
+    T1: 't1,
+    T2: 't2,
+{
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'tboth, (T1, T2)> {
+        let t1 = self.a1.take_owned();
+        let t2 = self.a2.take_owned();
+        t1.then(t2)
+    }
+}
+
+impl<A1, A2, T1, T2, F1, F2, O1, O2, E> PartialApply<(T1, T2), (F1, F2), (O1, O2), E>
     for Chain<A1, A2>
 where
     A1: PartialApply<T1, F1, O1, E>,
     A2: PartialApply<T2, F2, O2, E>,
 {
-    fn get_next(&self) -> (T1, T2) {
+    type Next = (A1::Next, A2::Next);
+
+    fn get_next(&self) -> Self::Next {
         let t1 = A1::get_next(&self.a1);
         let t2 = A2::get_next(&self.a2);
         (t1, t2)
@@ -51,20 +101,34 @@ where
 
     #[allow(clippy::type_complexity)]
     fn modify_next(
-        (next1, next2): (T1, T2),
+        &self,
+        (next1, next2): Self::Next,
         (f1, f2): (F1, F2),
-    ) -> Result<((O1, O2), (T1, T2)), E> {
-        let (o1, next1) = A1::modify_next(next1, f1)?;
-        let (o2, next2) = A2::modify_next(next2, f2)?;
+    ) -> Result<((O1, O2), Self::Next), E> {
+        let (o1, next1) = A1::modify_next(&self.a1, next1, f1)?;
+        let (o2, next2) = A2::modify_next(&self.a2, next2, f2)?;
         Ok(((o1, o2), (next1, next2)))
     }
 
-    fn replace(&mut self, (next1, next2): (T1, T2)) {
+    fn replace(&mut self, (next1, next2): Self::Next) {
         A1::replace(&mut self.a1, next1);
         A2::replace(&mut self.a2, next2);
     }
 }
 
+impl<'a, A1, A2, T1, T2, F1, F2, O1, O2, E> PartialPending<'a, (T1, T2), (F1, F2), (O1, O2), E>
+    for Chain<A1, A2>
+where
+    A1: PartialPending<'a, T1, F1, O1, E>,
+    A2: PartialPending<'a, T2, F2, O2, E>,
+{
+    type View = (A1::View, A2::View);
+
+    fn pending(&'a self, (next1, next2): &'a Self::Next) -> Self::View {
+        (self.a1.pending(next1), self.a2.pending(next2))
+    }
+}
+
 unsafe impl<'t1, 't2, 'tboth, A1, A2, T1, T2, F1, F2, O, E> Apply<'tboth, (T1, T2), (F1, F2), O, E>
     for Chain<A1, A2>
 where
@@ -81,9 +145,11 @@ where
         let f1: &mut F1 = self.a1.take_mut();
         let f2: &mut F2 = self.a2.take_mut();
 
+        let fs = (f1.clone(), f2.clone());
+
         // modify both copies
-        let (o, (next1, next2)) = match Self::modify_next(next, (f1.clone(), f2.clone())) {
-            Ok((v1, v2)) => (v1, v2),
+        let (o, next) = match self.modify_next(next, fs) {
+            Ok(v) => v,
             Err(e) => {
                 // Safety:
                 //
@@ -99,7 +165,7 @@ where
         //
         // only replace after both modifications were successfull
         // and after this, an `Ok` return is guaranteed
-        Self::replace(&mut self, (next1, next2));
+        Self::replace(&mut self, next);
 
         // Safety:
         //
@@ -115,3 +181,275 @@ where
         Ok((o, tokens))
     }
 }
+
+impl<A1, A2, T1, T2> PartialSwap<(T1, T2)> for Chain<A1, A2>
+where
+    A1: PartialSwap<T1>,
+    A2: PartialSwap<T2>,
+{
+    fn swap(&mut self, (next1, next2): (T1, T2)) -> (T1, T2) {
+        let old1 = A1::swap(&mut self.a1, next1);
+        let old2 = A2::swap(&mut self.a2, next2);
+        (old1, old2)
+    }
+}
+
+unsafe impl<'t1, 't2, 'tboth, A1, A2, T1, T2, F1, F2, O, E>
+    ApplyReversible<'tboth, (T1, T2), (F1, F2), O, E> for Chain<A1, A2>
+where
+    Self: PartialApply<(T1, T2), (F1, F2), O, E, Next = (T1, T2)> + PartialSwap<(T1, T2)>,
+    A1: Take<F1, target::Function> + TakeOwned<Token<'t1, T1>, target::Token>,
+    A2: Take<F2, target::Function> + TakeOwned<Token<'t2, T2>, target::Token>,
+    T1: 't1,
+    T2: 't2,
+    F1: Clone,
+    F2: Clone,
+{
+    #[allow(clippy::type_complexity)]
+    fn commit_reversible(
+        mut self,
+    ) -> crate::AllOrNone<'tboth, (O, Undo<Self, (T1, T2)>), E, (T1, T2)> {
+        let next = Self::get_next(&self);
+        let f1: &mut F1 = self.a1.take_mut();
+        let f2: &mut F2 = self.a2.take_mut();
+
+        let fs = (f1.clone(), f2.clone());
+
+        // modify both copies
+        let (o, next) = match self.modify_next(next, fs) {
+            Ok(v) => v,
+            Err(e) => {
+                // Safety:
+                //
+                // this is indicating that the mutation failed,
+                // and also preventing further mutations
+                let t: Token<(T1, T2)> = unsafe { self.take_owned() };
+                return Err((e, t));
+            }
+        };
+
+        // Safety:
+        //
+        // only swap after both modifications were successfull
+        // and after this, an `Ok` return is guaranteed
+        let old = Self::swap(&mut self, next);
+
+        // the original `Token`s stay inside of the `Undo`, in case
+        // the old values get rolled back
+        let consumed1 = ConsumedToken::<T1>::from(Token(PhantomData));
+        let consumed2 = ConsumedToken::<T2>::from(Token(PhantomData));
+        let tokens = consumed1.then(consumed2);
+        Ok(((o, Undo::new(self, old)), tokens))
+    }
+}
+
+impl<A1, A2, T1, T2, F1, F2, O1, O2, E> PartialScratch<(T1, T2), (F1, F2), (O1, O2), E>
+    for Chain<A1, A2>
+where
+    A1: PartialScratch<T1, F1, O1, E>,
+    A2: PartialScratch<T2, F2, O2, E>,
+{
+    fn clone_into_next(&self, (next1, next2): &mut (T1, T2)) {
+        A1::clone_into_next(&self.a1, next1);
+        A2::clone_into_next(&self.a2, next2);
+    }
+
+    fn modify_in_place(
+        &self,
+        (next1, next2): &mut (T1, T2),
+        (f1, f2): (F1, F2),
+    ) -> Result<(O1, O2), E> {
+        let o1 = A1::modify_in_place(&self.a1, next1, f1)?;
+        let o2 = A2::modify_in_place(&self.a2, next2, f2)?;
+        Ok((o1, o2))
+    }
+}
+
+unsafe impl<'t1, 't2, 'tboth, A1, A2, T1, T2, F1, F2, O, E>
+    ApplyScratch<'tboth, (T1, T2), (F1, F2), O, E> for Chain<A1, A2>
+where
+    Self: PartialApply<(T1, T2), (F1, F2), O, E, Next = (T1, T2)>
+        + PartialScratch<(T1, T2), (F1, F2), O, E>
+        + PartialSwap<(T1, T2)>,
+    A1: Take<F1, target::Function> + TakeOwned<Token<'t1, T1>, target::Token>,
+    A2: Take<F2, target::Function> + TakeOwned<Token<'t2, T2>, target::Token>,
+    T1: 't1,
+    T2: 't2,
+    F1: Clone,
+    F2: Clone,
+{
+    fn apply_with(
+        mut self,
+        scratch: &mut Scratch<(T1, T2)>,
+    ) -> crate::AllOrNone<'tboth, O, E, (T1, T2)> {
+        let mut next = match scratch.take() {
+            Some(mut next) => {
+                self.clone_into_next(&mut next);
+                next
+            }
+            None => Self::get_next(&self),
+        };
+        let f1: &mut F1 = self.a1.take_mut();
+        let f2: &mut F2 = self.a2.take_mut();
+
+        let fs = (f1.clone(), f2.clone());
+
+        // modify both copies
+        let o = match self.modify_in_place(&mut next, fs) {
+            Ok(o) => o,
+            Err(e) => {
+                scratch.put(next);
+                // Safety:
+                //
+                // this is indicating that the mutation failed,
+                // and also preventing further mutations
+                let t: Token<(T1, T2)> = unsafe { self.take_owned() };
+                return Err((e, t));
+            }
+        };
+
+        // Safety:
+        //
+        // only swap after both modifications were successfull
+        // and after this, an `Ok` return is guaranteed
+        let old = Self::swap(&mut self, next);
+        scratch.put(old);
+
+        // Safety:
+        //
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        let t: Token<(T1, T2)> = unsafe { self.take_owned() };
+        Ok((o, ConsumedToken::from(t)))
+    }
+}
+
+/// A `Chain` with a check over all of it's modified copies.
+///
+/// The check reads the copies through `PartialPending`, so a member in
+/// the `overlay` mode can't be checked, as it has no view.
+///
+/// See `Chain::with_check()`.
+pub struct CheckedChain<A1, A2, F1, F2, C> {
+    chain: Chain<A1, A2>,
+    fs: (F1, F2),
+    check: C,
+}
+
+impl<A1, A2, F1, F2, C> CheckedChain<A1, A2, F1, F2, C> {
+    /// Adds another modification, so that it's committed together with
+    /// this one. The check only covers the members of this one.
+    pub fn chain<A3>(self, a3: A3) -> Chain<Self, A3> {
+        Chain::new(self, a3)
+    }
+}
+
+impl<A1, A2, F1, F2, C> Take<(F1, F2), target::Function> for CheckedChain<A1, A2, F1, F2, C> {
+    fn take_ref(&self) -> &(F1, F2) {
+        &self.fs
+    }
+
+    fn take_mut(&mut self) -> &mut (F1, F2) {
+        &mut self.fs
+    }
+}
+
+impl<'tboth, A1, A2, F1, F2, C, T1, T2> TakeOwned<Token<'tboth, (T1, T2)>, target::Token>
+    for CheckedChain<A1, A2, F1, F2, C>
+where
+    Chain<A1, A2>: TakeOwned<Token<'tboth, (T1, T2)>, target::Token>,
+{
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'tboth, (T1, T2)> {
+        self.chain.take_owned()
+    }
+}
+
+impl<A1, A2, C, T1, T2, F1, F2, O, E> PartialApply<(T1, T2), (F1, F2), O, E>
+    for CheckedChain<A1, A2, F1, F2, C>
+where
+    Chain<A1, A2>: for<'a> PartialPending<'a, (T1, T2), (F1, F2), O, E>,
+    C: for<'a> Fn(
+        <Chain<A1, A2> as PartialPending<'a, (T1, T2), (F1, F2), O, E>>::View,
+    ) -> Result<(), E>,
+{
+    type Next = <Chain<A1, A2> as PartialApply<(T1, T2), (F1, F2), O, E>>::Next;
+
+    fn get_next(&self) -> Self::Next {
+        self.chain.get_next()
+    }
+
+    fn modify_next(&self, next: Self::Next, fs: (F1, F2)) -> Result<(O, Self::Next), E> {
+        let (o, next) = self.chain.modify_next(next, fs)?;
+        (self.check)(self.chain.pending(&next))?;
+        Ok((o, next))
+    }
+
+    fn replace(&mut self, next: Self::Next) {
+        self.chain.replace(next)
+    }
+}
+
+impl<'a, A1, A2, C, T1, T2, F1, F2, O, E> PartialPending<'a, (T1, T2), (F1, F2), O, E>
+    for CheckedChain<A1, A2, F1, F2, C>
+where
+    Self: PartialApply<
+        (T1, T2),
+        (F1, F2),
+        O,
+        E,
+        Next = <Chain<A1, A2> as PartialApply<(T1, T2), (F1, F2), O, E>>::Next,
+    >,
+    Chain<A1, A2>: PartialPending<'a, (T1, T2), (F1, F2), O, E>,
+{
+    type View = <Chain<A1, A2> as PartialPending<'a, (T1, T2), (F1, F2), O, E>>::View;
+
+    fn pending(&'a self, next: &'a Self::Next) -> Self::View {
+        self.chain.pending(next)
+    }
+}
+
+unsafe impl<'t1, 't2, 'tboth, A1, A2, C, T1, T2, F1, F2, O, E>
+    Apply<'tboth, (T1, T2), (F1, F2), O, E> for CheckedChain<A1, A2, F1, F2, C>
+where
+    Self: PartialApply<(T1, T2), (F1, F2), O, E>,
+    A1: TakeOwned<Token<'t1, T1>, target::Token>,
+    A2: TakeOwned<Token<'t2, T2>, target::Token>,
+    T1: 't1,
+    T2: 't2,
+    F1: Clone,
+    F2: Clone,
+{
+    fn apply(mut self) -> crate::AllOrNone<'tboth, O, E, (T1, T2)> {
+        let next = Self::get_next(&self);
+        let fs = self.fs.clone();
+
+        // modify both copies, and then check them
+        let (o, next) = match self.modify_next(next, fs) {
+            Ok(v) => v,
+            Err(e) => {
+                // Safety:
+                //
+                // this is indicating that the mutation or the check
+                // failed, and also preventing further mutations
+                let t: Token<(T1, T2)> = unsafe { self.take_owned() };
+                return Err((e, t));
+            }
+        };
+
+        // Safety:
+        //
+        // only replace after both modifications and the check were
+        // successfull, and after this, an `Ok` return is guaranteed
+        Self::replace(&mut self, next);
+
+        // Safety:
+        //
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        let t: Token<(T1, T2)> = unsafe { self.take_owned() };
+        Ok((o, ConsumedToken::from(t)))
+    }
+}
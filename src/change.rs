@@ -0,0 +1,319 @@
+use crate::prepared::mode;
+use crate::{target, OneMut, PartialApply, PartialPending, Prepared, Take, Token};
+
+/// Whether the appliance actually changed `T`.
+///
+/// In either case the `Token` gets consumed, as `T` was still accessed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The modified copy differed from the original `T`, which got replaced.
+    Changed,
+    /// The modified copy was equal to the original `T`, which was kept
+    /// in place.
+    Unchanged,
+}
+
+impl Change {
+    pub fn is_changed(&self) -> bool {
+        *self == Change::Changed
+    }
+}
+
+/// The error of a "must change" appliance whose modified copy was equal
+/// to the original `T`.
+///
+/// See `OneMut::prepare_must_change()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Unchanged;
+
+/// A hash of the contents of `T`, used to detect modifications that
+/// changed `T` without comparing the whole `T`.
+///
+/// Two values with different hashes are treated as different, and two
+/// values with the same hash are still compared by `PartialEq`, so
+/// collisions don't make a change be discarded. Equal values must have
+/// the same hash.
+pub trait ContentHash {
+    fn content_hash(&self) -> u64;
+}
+
+impl<'t, T> OneMut<'t, T> {
+    /// Defines how `T` should be mutated, given an `Ok` response, while
+    /// skipping the replacement of `T` if the copy ends up equal to it.
+    ///
+    /// The copy and the original `T` are compared by `PartialEq`, and the
+    /// appliance returns a `Change` alongside the closure's output.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_compared<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Compared> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `OneMut::prepare_compared()`.
+    pub fn unchecked_prepare_compared<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Compared> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// Defines how `T` should be mutated, given an `Ok` response, while
+    /// skipping the replacement of `T` if the copy ends up equal to it.
+    ///
+    /// The copy and the original `T` are compared by `ContentHash`, and
+    /// only if the hashes are equal, also by `PartialEq`. The appliance
+    /// returns a `Change` alongside the closure's output.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_hashed<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Hashed> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `OneMut::prepare_hashed()`.
+    pub fn unchecked_prepare_hashed<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Hashed> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// Defines how `T` should be mutated, given an `Ok` response, while
+    /// requiring that `T` ends up changed.
+    ///
+    /// The copy and the original `T` are compared by `PartialEq`, and if
+    /// they're equal, the appliance fails with `E::from(Unchanged)`,
+    /// giving back the `Token`. This catches modifications that were
+    /// mistakenly forgotten.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_must_change<F, E>(
+        self,
+        f: F,
+    ) -> Prepared<Self, T, F, E, mode::MustChange> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `OneMut::prepare_must_change()`.
+    pub fn unchecked_prepare_must_change<F, E>(
+        self,
+        f: F,
+    ) -> Prepared<Self, T, F, E, mode::MustChange> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// Same as `OneMut::prepare_must_change()`, but `T` is considered
+    /// changed if `changed(original, copy)` is true.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_must_change_by<F, P, E>(
+        self,
+        f: F,
+        changed: P,
+    ) -> Prepared<Self, T, (F, P), E, mode::MustChangeBy> {
+        Prepared::with_mode(self, (f, changed))
+    }
+
+    /// See `OneMut::prepare_must_change_by()`.
+    pub fn unchecked_prepare_must_change_by<F, P, E>(
+        self,
+        f: F,
+        changed: P,
+    ) -> Prepared<Self, T, (F, P), E, mode::MustChangeBy> {
+        Prepared::with_mode(self, (f, changed))
+    }
+}
+
+impl<'t, OuterT, T, F, O, E> PartialApply<T, F, (O, Change), E>
+    for Prepared<OuterT, T, F, E, mode::Compared>
+where
+    OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
+    F: FnOnce(&mut T) -> Result<O, E>,
+    T: 't + Clone + PartialEq,
+    OuterT: 't,
+{
+    /// The copy, and whether it's different from the original `T`.
+    type Next = (T, Change);
+
+    fn get_next(&self) -> (T, Change) {
+        let next: &T = self.inner.take_ref();
+        (next.clone(), Change::Unchanged)
+    }
+
+    fn modify_next(
+        &self,
+        (mut next, _): (T, Change),
+        f: F,
+    ) -> Result<((O, Change), (T, Change)), E> {
+        let o = (f)(&mut next)?;
+        let original: &T = self.inner.take_ref();
+        let change = if next == *original {
+            Change::Unchanged
+        } else {
+            Change::Changed
+        };
+        Ok(((o, change), (next, change)))
+    }
+
+    /// Only replaces `T` if it got changed.
+    fn replace(&mut self, (next, change): (T, Change)) {
+        if change.is_changed() {
+            let current: &mut T = self.inner.take_mut();
+            *current = next;
+        }
+    }
+}
+
+impl<'a, OuterT, T, F, O, E> PartialPending<'a, T, F, (O, Change), E>
+    for Prepared<OuterT, T, F, E, mode::Compared>
+where
+    Self: PartialApply<T, F, (O, Change), E, Next = (T, Change)>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, (next, _): &'a (T, Change)) -> &'a T {
+        next
+    }
+}
+
+impl<'t, OuterT, T, F, O, E> PartialApply<T, F, (O, Change), E>
+    for Prepared<OuterT, T, F, E, mode::Hashed>
+where
+    OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
+    F: FnOnce(&mut T) -> Result<O, E>,
+    T: 't + Clone + ContentHash + PartialEq,
+    OuterT: 't,
+{
+    /// The copy, and whether it's different from the original `T`.
+    type Next = (T, Change);
+
+    fn get_next(&self) -> (T, Change) {
+        let next: &T = self.inner.take_ref();
+        (next.clone(), Change::Unchanged)
+    }
+
+    fn modify_next(
+        &self,
+        (mut next, _): (T, Change),
+        f: F,
+    ) -> Result<((O, Change), (T, Change)), E> {
+        let o = (f)(&mut next)?;
+        let original: &T = self.inner.take_ref();
+        // a different hash is always a change, and an equal hash may
+        // still be a collision
+        let change = if next.content_hash() != original.content_hash() || next != *original {
+            Change::Changed
+        } else {
+            Change::Unchanged
+        };
+        Ok(((o, change), (next, change)))
+    }
+
+    /// Only replaces `T` if it got changed.
+    fn replace(&mut self, (next, change): (T, Change)) {
+        if change.is_changed() {
+            let current: &mut T = self.inner.take_mut();
+            *current = next;
+        }
+    }
+}
+
+impl<'a, OuterT, T, F, O, E> PartialPending<'a, T, F, (O, Change), E>
+    for Prepared<OuterT, T, F, E, mode::Hashed>
+where
+    Self: PartialApply<T, F, (O, Change), E, Next = (T, Change)>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, (next, _): &'a (T, Change)) -> &'a T {
+        next
+    }
+}
+
+impl<'t, OuterT, T, F, O, E> PartialApply<T, F, O, E>
+    for Prepared<OuterT, T, F, E, mode::MustChange>
+where
+    OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
+    F: FnOnce(&mut T) -> Result<O, E>,
+    T: 't + Clone + PartialEq,
+    E: From<Unchanged>,
+    OuterT: 't,
+{
+    type Next = T;
+
+    fn get_next(&self) -> T {
+        let next: &T = self.inner.take_ref();
+        next.clone()
+    }
+
+    fn modify_next(&self, mut next: T, f: F) -> Result<(O, T), E> {
+        let o = (f)(&mut next)?;
+        let original: &T = self.inner.take_ref();
+        if next == *original {
+            return Err(E::from(Unchanged));
+        }
+        Ok((o, next))
+    }
+
+    fn replace(&mut self, next: T) {
+        let current: &mut T = self.inner.take_mut();
+        *current = next;
+    }
+}
+
+impl<'a, OuterT, T, F, O, E> PartialPending<'a, T, F, O, E>
+    for Prepared<OuterT, T, F, E, mode::MustChange>
+where
+    Self: PartialApply<T, F, O, E, Next = T>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, next: &'a T) -> &'a T {
+        next
+    }
+}
+
+impl<'t, OuterT, T, F, P, O, E> PartialApply<T, (F, P), O, E>
+    for Prepared<OuterT, T, (F, P), E, mode::MustChangeBy>
+where
+    OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
+    F: FnOnce(&mut T) -> Result<O, E>,
+    P: FnOnce(&T, &T) -> bool,
+    T: 't + Clone,
+    E: From<Unchanged>,
+    OuterT: 't,
+{
+    type Next = T;
+
+    fn get_next(&self) -> T {
+        let next: &T = self.inner.take_ref();
+        next.clone()
+    }
+
+    fn modify_next(&self, mut next: T, (f, changed): (F, P)) -> Result<(O, T), E> {
+        let o = (f)(&mut next)?;
+        let original: &T = self.inner.take_ref();
+        if !(changed)(original, &next) {
+            return Err(E::from(Unchanged));
+        }
+        Ok((o, next))
+    }
+
+    fn replace(&mut self, next: T) {
+        let current: &mut T = self.inner.take_mut();
+        *current = next;
+    }
+}
+
+impl<'a, OuterT, T, F, P, O, E> PartialPending<'a, T, (F, P), O, E>
+    for Prepared<OuterT, T, (F, P), E, mode::MustChangeBy>
+where
+    Self: PartialApply<T, (F, P), O, E, Next = T>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, next: &'a T) -> &'a T {
+        next
+    }
+}
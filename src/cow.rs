@@ -0,0 +1,192 @@
+use crate::prepared::mode;
+use crate::{target, OneMut, PartialApply, PartialPending, Prepared, Take, Token};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+/// Copy-on-write access into an `Arc<T>`.
+///
+/// Shared accesses read from the `Arc<T>`, and the first mut access
+/// clones the `T` (with `Arc::make_mut`), if it's shared.
+pub struct ArcMut<T>(Arc<T>);
+
+impl<T> Deref for ArcMut<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Clone> DerefMut for ArcMut<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+/// Clone-on-write access into a `T`.
+///
+/// Shared accesses read from the original `T`, and the first mut access
+/// clones it into a copy, which then receives all further accesses.
+pub struct CowMut<'a, T> {
+    original: &'a T,
+    copy: Option<T>,
+}
+
+impl<'a, T> CowMut<'a, T> {
+    /// Whether `T` got mutably accessed, ie. whether it got cloned.
+    pub fn is_written(&self) -> bool {
+        self.copy.is_some()
+    }
+}
+
+impl<'a, T> Deref for CowMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match &self.copy {
+            Some(copy) => copy,
+            None => self.original,
+        }
+    }
+}
+
+impl<'a, T: Clone> DerefMut for CowMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        let original = self.original;
+        self.copy.get_or_insert_with(|| original.clone())
+    }
+}
+
+impl<'t, T> OneMut<'t, T> {
+    /// Defines how `T` should be mutated, given an `Ok` response, without
+    /// cloning `T` unless it's mutably accessed.
+    ///
+    /// The closure receives a `CowMut<T>`, which only clones `T` on the
+    /// first mut access. If the closure never mutably accesses `T`, the
+    /// appliance skips both the clone and the replacement of `T`, but the
+    /// `Token` still gets consumed.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_lazy<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Lazy> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `OneMut::prepare_lazy()`.
+    pub fn unchecked_prepare_lazy<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Lazy> {
+        Prepared::with_mode(self, f)
+    }
+}
+
+impl<'t, T> OneMut<'t, Arc<T>> {
+    /// Defines how `T` should be mutated, given an `Ok` response, without
+    /// cloning `T` unless it's mutably accessed.
+    ///
+    /// The closure receives an `ArcMut<T>`, which only clones `T` on the
+    /// first mut access. If the closure never mutably accesses `T`, the
+    /// appliance places the same `Arc<T>` back, without any allocations.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_cow<F, E>(self, f: F) -> Prepared<Self, Arc<T>, F, E, mode::MakeMut> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `OneMut::prepare_cow()`.
+    pub fn unchecked_prepare_cow<F, E>(self, f: F) -> Prepared<Self, Arc<T>, F, E, mode::MakeMut> {
+        Prepared::with_mode(self, f)
+    }
+}
+
+impl<'t, OuterT, T, F, O, E> PartialApply<Arc<T>, F, O, E>
+    for Prepared<OuterT, Arc<T>, F, E, mode::MakeMut>
+where
+    OuterT: Take<Arc<T>, target::Type> + Take<Token<'t, Arc<T>>, target::Token>,
+    F: FnOnce(&mut ArcMut<T>) -> Result<O, E>,
+    T: 't + Clone,
+    OuterT: 't,
+{
+    type Next = Arc<T>;
+
+    /// Only clones the `Arc`.
+    fn get_next(&self) -> Arc<T> {
+        let next: &Arc<T> = self.inner.take_ref();
+        Arc::clone(next)
+    }
+
+    fn modify_next(&self, next: Arc<T>, f: F) -> Result<(O, Arc<T>), E> {
+        let mut next = ArcMut(next);
+        let o = (f)(&mut next)?;
+        Ok((o, next.0))
+    }
+
+    fn replace(&mut self, next: Arc<T>) {
+        let current: &mut Arc<T> = self.inner.take_mut();
+        *current = next;
+    }
+}
+
+impl<'a, OuterT, T, F, O, E> PartialPending<'a, Arc<T>, F, O, E>
+    for Prepared<OuterT, Arc<T>, F, E, mode::MakeMut>
+where
+    Self: PartialApply<Arc<T>, F, O, E, Next = Arc<T>>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, next: &'a Arc<T>) -> &'a T {
+        next
+    }
+}
+
+impl<'t, OuterT, T, F, O, E> PartialApply<T, F, O, E> for Prepared<OuterT, T, F, E, mode::Lazy>
+where
+    OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
+    F: for<'a> FnOnce(&mut CowMut<'a, T>) -> Result<O, E>,
+    T: 't + Clone,
+    OuterT: 't,
+{
+    /// The copy, if `T` got mutably accessed.
+    type Next = Option<T>;
+
+    /// Doesn't clone `T`.
+    fn get_next(&self) -> Option<T> {
+        None
+    }
+
+    fn modify_next(&self, next: Option<T>, f: F) -> Result<(O, Option<T>), E> {
+        let original: &T = self.inner.take_ref();
+        let mut next = CowMut {
+            original,
+            copy: next,
+        };
+        let o = (f)(&mut next)?;
+        Ok((o, next.copy))
+    }
+
+    /// Only replaces `T` if it got mutably accessed.
+    fn replace(&mut self, next: Option<T>) {
+        if let Some(next) = next {
+            let current: &mut T = self.inner.take_mut();
+            *current = next;
+        }
+    }
+}
+
+impl<'a, OuterT, T, F, O, E> PartialPending<'a, T, F, O, E>
+    for Prepared<OuterT, T, F, E, mode::Lazy>
+where
+    Self: PartialApply<T, F, O, E, Next = Option<T>>,
+    OuterT: Take<T, target::Type>,
+{
+    type View = &'a T;
+
+    /// The original `T`, if it was not mutably accessed.
+    fn pending(&'a self, next: &'a Option<T>) -> &'a T {
+        match next {
+            Some(next) => next,
+            None => self.inner.take_ref(),
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use super::prepared::mode;
+use super::{
+    and_then, target, AndThen, Apply, Chain, ConsumedToken, OneMut, PartialApply, PartialPending,
+    Prepared, Take, TakeOwned, Token,
+};
+use std::marker::PhantomData;
+
+/// One of two alternatives.
+///
+/// As an output, it records which of the alternatives got committed.
+/// As a holder of one of two `Prepared` modifications (with different
+/// closure types) into the same `T`, it can be turned into a single
+/// member by `Either::branch()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<L, R> Either<L, R> {
+    pub fn is_left(&self) -> bool {
+        matches!(self, Either::Left(_))
+    }
+
+    pub fn is_right(&self) -> bool {
+        matches!(self, Either::Right(_))
+    }
+
+    /// Turns one of two modifications into a single member, which can
+    /// be applied or chained.
+    pub fn branch<FA, FB>(self) -> Branch<L, R, FA, FB>
+    where
+        L: Take<FA, target::Function>,
+        R: Take<FB, target::Function>,
+        FA: Clone,
+        FB: Clone,
+    {
+        let f = match &self {
+            Either::Left(pa) => Either::Left(pa.take_ref().clone()),
+            Either::Right(pb) => Either::Right(pb.take_ref().clone()),
+        };
+        Branch { either: self, f }
+    }
+}
+
+/// One of two modifications into the same `T`, with different closure
+/// types, as a single member.
+///
+/// The output records which of the modifications got committed.
+///
+/// See `Either::branch()`.
+pub struct Branch<PA, PB, FA, FB> {
+    either: Either<PA, PB>,
+    f: Either<FA, FB>,
+}
+
+impl<PA, PB, FA, FB> Branch<PA, PB, FA, FB> {
+    /// Chains this modification with another one, so that both are
+    /// committed in the same transaction.
+    pub fn chain<A2>(self, a2: A2) -> Chain<Self, A2> {
+        Chain::new(self, a2)
+    }
+
+    /// Adds a modification into `T2`, whose closure receives the output
+    /// of this one, see `Prepared::and_then()`.
+    pub fn and_then<'t2, T2, G2>(
+        self,
+        b: OneMut<'t2, T2>,
+        g2: G2,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, Either<FA, FB>, G2>
+    where
+        FA: Clone,
+        FB: Clone,
+    {
+        AndThen::new(self, b, g2)
+    }
+
+    /// Adds a modification into `T2`, whose closure can read the modified
+    /// copy of this one, see `Prepared::and_then_pending()`.
+    #[allow(clippy::type_complexity)]
+    pub fn and_then_pending<'t2, T2, G2>(
+        self,
+        b: OneMut<'t2, T2>,
+        g2: G2,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, Either<FA, FB>, G2, and_then::link::Pending>
+    where
+        FA: Clone,
+        FB: Clone,
+    {
+        AndThen::new(self, b, g2)
+    }
+}
+
+impl<PA, PB, FA, FB> Take<Either<FA, FB>, target::Function> for Branch<PA, PB, FA, FB> {
+    fn take_ref(&self) -> &Either<FA, FB> {
+        &self.f
+    }
+
+    fn take_mut(&mut self) -> &mut Either<FA, FB> {
+        &mut self.f
+    }
+}
+
+impl<'t, PA, PB, FA, FB, T> TakeOwned<Token<'t, T>, target::Token> for Branch<PA, PB, FA, FB>
+where
+    PA: TakeOwned<Token<'t, T>, target::Token>,
+    PB: TakeOwned<Token<'t, T>, target::Token>,
+{
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'t, T> {
+        match self.either {
+            Either::Left(pa) => pa.take_owned(),
+            Either::Right(pb) => pb.take_owned(),
+        }
+    }
+}
+
+impl<PA, PB, T, FA, FB, OA, OB, E> PartialApply<T, Either<FA, FB>, Either<OA, OB>, E>
+    for Branch<PA, PB, FA, FB>
+where
+    PA: PartialApply<T, FA, OA, E>,
+    PB: PartialApply<T, FB, OB, E>,
+{
+    type Next = Either<PA::Next, PB::Next>;
+
+    fn get_next(&self) -> Self::Next {
+        match &self.either {
+            Either::Left(pa) => Either::Left(pa.get_next()),
+            Either::Right(pb) => Either::Right(pb.get_next()),
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn modify_next(
+        &self,
+        next: Self::Next,
+        f: Either<FA, FB>,
+    ) -> Result<(Either<OA, OB>, Self::Next), E> {
+        match (&self.either, next, f) {
+            (Either::Left(pa), Either::Left(next), Either::Left(fa)) => {
+                let (o, next) = pa.modify_next(next, fa)?;
+                Ok((Either::Left(o), Either::Left(next)))
+            }
+            (Either::Right(pb), Either::Right(next), Either::Right(fb)) => {
+                let (o, next) = pb.modify_next(next, fb)?;
+                Ok((Either::Right(o), Either::Right(next)))
+            }
+            _ => unreachable!("the copy and the closure always come from the same branch"),
+        }
+    }
+
+    fn replace(&mut self, next: Self::Next) {
+        match (&mut self.either, next) {
+            (Either::Left(pa), Either::Left(next)) => pa.replace(next),
+            (Either::Right(pb), Either::Right(next)) => pb.replace(next),
+            _ => unreachable!("the copy always comes from the same branch"),
+        }
+    }
+}
+
+unsafe impl<'t, PA, PB, T, FA, FB, O, E> Apply<'t, T, Either<FA, FB>, O, E>
+    for Branch<PA, PB, FA, FB>
+where
+    Self: PartialApply<T, Either<FA, FB>, O, E>,
+    PA: TakeOwned<Token<'t, T>, target::Token>,
+    PB: TakeOwned<Token<'t, T>, target::Token>,
+    T: 't,
+    FA: Clone,
+    FB: Clone,
+{
+    fn apply(mut self) -> crate::AllOrNone<'t, O, E, T> {
+        let next = self.get_next();
+        let f = self.f.clone();
+
+        let (o, next) = match self.modify_next(next, f) {
+            Ok(v) => v,
+            Err(e) => {
+                // Safety:
+                //
+                // this is indicating that the mutation failed,
+                // and also preventing further mutations
+                let t = unsafe { self.take_owned() };
+                return Err((e, t));
+            }
+        };
+        // Safety:
+        //
+        // only replace after the modifications were successful.
+        // Also, after this, an `Ok` return is guaranteed
+        self.replace(next);
+
+        // Safety:
+        //
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        let t = unsafe { self.take_owned() };
+        Ok((o, ConsumedToken::from(t)))
+    }
+}
+
+/// A modification with a fallback.
+///
+/// During `apply`, if the first closure fails, the fallback closure
+/// receives it's error and a fresh copy of `T`, so that the partial
+/// modifications of the first closure are discarded. The output
+/// records which of the closures got committed.
+///
+/// See `Prepared::or_else()`.
+pub struct OrElse<A, F, G, E1> {
+    a: A,
+    fs: (F, G),
+    _err: PhantomData<E1>,
+}
+
+impl<OuterT, T, F, E> Prepared<OuterT, T, F, E, mode::Copied> {
+    /// Adds a fallback modification, for when this one fails.
+    ///
+    /// `g` receives the error and a fresh copy of `T`, and it's output
+    /// and error are `O2` and `E2`. The appliance outputs `Left(O)` if
+    /// this modification got committed, or `Right(O2)` if the fallback
+    /// got committed instead.
+    ///
+    /// Only available in the default mode, as the fallback modifies the
+    /// copy directly, which would otherwise skip the checks of the mode.
+    pub fn or_else<G>(self, g: G) -> OrElse<Self, F, G, E>
+    where
+        F: Clone,
+    {
+        let f = self.f.clone();
+        OrElse {
+            a: self,
+            fs: (f, g),
+            _err: PhantomData,
+        }
+    }
+}
+
+impl<A, F, G, E1> OrElse<A, F, G, E1> {
+    /// Chains this modification with another one, so that both are
+    /// committed in the same transaction.
+    pub fn chain<A2>(self, a2: A2) -> Chain<Self, A2> {
+        Chain::new(self, a2)
+    }
+
+    /// Adds a modification into `T2`, whose closure receives the output
+    /// of this one, see `Prepared::and_then()`.
+    pub fn and_then<'t2, T2, G2>(
+        self,
+        b: OneMut<'t2, T2>,
+        g2: G2,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, (F, G), G2>
+    where
+        F: Clone,
+        G: Clone,
+    {
+        AndThen::new(self, b, g2)
+    }
+
+    /// Adds a modification into `T2`, whose closure can read the modified
+    /// copy of this one, see `Prepared::and_then_pending()`.
+    #[allow(clippy::type_complexity)]
+    pub fn and_then_pending<'t2, T2, G2>(
+        self,
+        b: OneMut<'t2, T2>,
+        g2: G2,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, (F, G), G2, and_then::link::Pending>
+    where
+        F: Clone,
+        G: Clone,
+    {
+        AndThen::new(self, b, g2)
+    }
+}
+
+impl<A, F, G, E1> Take<(F, G), target::Function> for OrElse<A, F, G, E1> {
+    fn take_ref(&self) -> &(F, G) {
+        &self.fs
+    }
+
+    fn take_mut(&mut self) -> &mut (F, G) {
+        &mut self.fs
+    }
+}
+
+impl<'t, A, F, G, E1, T> TakeOwned<Token<'t, T>, target::Token> for OrElse<A, F, G, E1>
+where
+    A: TakeOwned<Token<'t, T>, target::Token>,
+{
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'t, T> {
+        self.a.take_owned()
+    }
+}
+
+impl<A, T, F, G, O1, O2, E1, E2> PartialApply<T, (F, G), Either<O1, O2>, E2> for OrElse<A, F, G, E1>
+where
+    A: PartialApply<T, F, O1, E1, Next = T>,
+    G: FnOnce(E1, &mut T) -> Result<O2, E2>,
+{
+    type Next = T;
+
+    fn get_next(&self) -> T {
+        self.a.get_next()
+    }
+
+    fn modify_next(&self, next: T, (f, g): (F, G)) -> Result<(Either<O1, O2>, T), E2> {
+        match self.a.modify_next(next, f) {
+            Ok((o, next)) => Ok((Either::Left(o), next)),
+            Err(e) => {
+                // the partially modified copy was discarded
+                let mut next = self.a.get_next();
+                let o = (g)(e, &mut next)?;
+                Ok((Either::Right(o), next))
+            }
+        }
+    }
+
+    fn replace(&mut self, next: T) {
+        self.a.replace(next)
+    }
+}
+
+impl<'a, A, T, F, G, O1, O2, E1, E2> PartialPending<'a, T, (F, G), Either<O1, O2>, E2>
+    for OrElse<A, F, G, E1>
+where
+    Self: PartialApply<T, (F, G), Either<O1, O2>, E2, Next = T>,
+    A: PartialPending<'a, T, F, O1, E1, Next = T>,
+{
+    type View = A::View;
+
+    fn pending(&'a self, next: &'a T) -> A::View {
+        self.a.pending(next)
+    }
+}
+
+unsafe impl<'t, A, T, F, G, E1, O, E> Apply<'t, T, (F, G), O, E> for OrElse<A, F, G, E1>
+where
+    Self: PartialApply<T, (F, G), O, E>,
+    A: TakeOwned<Token<'t, T>, target::Token>,
+    T: 't,
+    F: Clone,
+    G: Clone,
+{
+    fn apply(mut self) -> crate::AllOrNone<'t, O, E, T> {
+        let next = self.get_next();
+        let fs = self.fs.clone();
+
+        let (o, next) = match self.modify_next(next, fs) {
+            Ok(v) => v,
+            Err(e) => {
+                // Safety:
+                //
+                // this is indicating that both the mutation and the
+                // fallback failed, and also preventing further mutations
+                let t = unsafe { self.take_owned() };
+                return Err((e, t));
+            }
+        };
+        // Safety:
+        //
+        // only replace after the modifications were successful.
+        // Also, after this, an `Ok` return is guaranteed
+        self.replace(next);
+
+        // Safety:
+        //
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        let t = unsafe { self.take_owned() };
+        Ok((o, ConsumedToken::from(t)))
+    }
+}
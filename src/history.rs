@@ -0,0 +1,225 @@
+use crate::prepared::mode;
+use crate::{
+    target, AllOrNone, OneMut, PartialApply, PartialPending, Prepared, Take, TakeOwned, Token,
+};
+use std::collections::VecDeque;
+
+/// Indicates that an `undo()` or `redo()` had no state to go into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryError {
+    NothingToUndo,
+    NothingToRedo,
+}
+
+/// Owns a `T` and records it's previous states.
+///
+/// Each `edit()` hands out a single `Edit` for `T`, which is prepared
+/// like a `OneMut`, and only successful appliances get recorded. The
+/// state that gets pushed into the undo stack is the old original `T`
+/// itself, so no further copies are made besides the one from the
+/// `Prepared` modification, and any sharing that `T` has (such as `Arc`
+/// fields) is kept.
+///
+/// `undo()` and `redo()` are also single mutations into `T`,
+/// returning a `ConsumedToken` on success and a `Token` when there
+/// was no state to go into.
+pub struct History<T> {
+    current: T,
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    depth: usize,
+}
+
+/// Allows shared access into the current state.
+impl<T> AsRef<T> for History<T> {
+    fn as_ref(&self) -> &T {
+        &self.current
+    }
+}
+
+impl<T> History<T> {
+    /// Creates a history that has no depth limit.
+    pub fn new(current: T) -> Self {
+        Self::with_depth(current, usize::MAX)
+    }
+
+    /// Creates a history that keeps at most `depth` undo states,
+    /// discarding the oldest ones.
+    pub fn with_depth(current: T, depth: usize) -> Self {
+        Self {
+            current,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+        }
+    }
+
+    /// Shared access into the current state.
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    /// The maximum amount of undo states that are kept, after which the
+    /// oldest ones are discarded.
+    ///
+    /// A depth of `0` keeps no undo states, and `History::new()` has no
+    /// limit, ie. a depth of `usize::MAX`.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// How many `undo()`s are available.
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// How many `redo()`s are available.
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Discards the history, returning the current state.
+    pub fn into_inner(self) -> T {
+        self.current
+    }
+
+    /// Hands out a single modification into the current state.
+    ///
+    /// The `Edit` is prepared like a `OneMut`, and so it can also be
+    /// chained with other modifications. Once it's appliance succeeds,
+    /// the previous state is pushed into the undo stack and the redo
+    /// stack is cleared. On failure, the current state and both stacks
+    /// are kept intact.
+    pub fn edit(&mut self) -> Edit<'_, T> {
+        Edit {
+            inner: OneMut::new(&mut self.current),
+            undo: &mut self.undo,
+            redo: &mut self.redo,
+            depth: self.depth,
+        }
+    }
+
+    /// Goes back into the previous state, if any.
+    pub fn undo(&mut self) -> AllOrNone<'_, (), HistoryError, T> {
+        let mut one_mut = OneMut::new(&mut self.current);
+        let prev = match self.undo.pop_back() {
+            Some(prev) => prev,
+            None => return Err((HistoryError::NothingToUndo, one_mut.unchecked_token())),
+        };
+        let current: &mut T = one_mut.take_mut();
+        let next = std::mem::replace(current, prev);
+        self.redo.push(next);
+        Ok(((), one_mut.unchecked_consume()))
+    }
+
+    /// Goes back into the state that got undone, if any.
+    pub fn redo(&mut self) -> AllOrNone<'_, (), HistoryError, T> {
+        let mut one_mut = OneMut::new(&mut self.current);
+        let next = match self.redo.pop() {
+            Some(next) => next,
+            None => return Err((HistoryError::NothingToRedo, one_mut.unchecked_token())),
+        };
+        let current: &mut T = one_mut.take_mut();
+        let prev = std::mem::replace(current, next);
+        self.undo.push_back(prev);
+        Ok(((), one_mut.unchecked_consume()))
+    }
+}
+
+/// A single modification into the current state of a `History`.
+///
+/// See `History::edit()`.
+pub struct Edit<'h, T> {
+    inner: OneMut<'h, T>,
+    undo: &'h mut VecDeque<T>,
+    redo: &'h mut Vec<T>,
+    depth: usize,
+}
+
+impl<'h, T> Edit<'h, T> {
+    /// Defines how `T` should be mutated, given an `Ok` response.
+    ///
+    /// See `OneMut::prepare()` for the `Result` signaling.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Recorded> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `Edit::prepare()`.
+    pub fn unchecked_prepare<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Recorded> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// Pushes the replaced state into the undo stack, and clears the
+    /// redo stack.
+    fn record(&mut self, old: T) {
+        if self.depth != 0 {
+            if self.undo.len() == self.depth {
+                let _oldest = self.undo.pop_front();
+            }
+            self.undo.push_back(old);
+        }
+        self.redo.clear();
+    }
+}
+
+impl<'h, T> Take<Token<'h, T>, target::Token> for Edit<'h, T> {
+    fn take_ref(&self) -> &Token<'h, T> {
+        self.inner.take_ref()
+    }
+
+    fn take_mut(&mut self) -> &mut Token<'h, T> {
+        self.inner.take_mut()
+    }
+}
+
+impl<'h, T> TakeOwned<Token<'h, T>, target::Token> for Edit<'h, T> {
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'h, T> {
+        self.inner.take_owned()
+    }
+}
+
+// `Edit` doesn't give out `Take<T, target::Type>`, so that `T` can only
+// be replaced by `replace()`, which records it
+impl<'h, T, F, O, E> PartialApply<T, F, O, E> for Prepared<Edit<'h, T>, T, F, E, mode::Recorded>
+where
+    F: FnOnce(&mut T) -> Result<O, E>,
+    T: Clone,
+{
+    type Next = T;
+
+    fn get_next(&self) -> T {
+        let current: &T = self.inner.inner.take_ref();
+        current.clone()
+    }
+
+    fn modify_next(&self, mut next: T, f: F) -> Result<(O, T), E> {
+        let o = (f)(&mut next)?;
+        Ok((o, next))
+    }
+
+    /// Records the replaced `T`.
+    fn replace(&mut self, next: T) {
+        let current: &mut T = self.inner.inner.take_mut();
+        let old = std::mem::replace(current, next);
+        self.inner.record(old);
+    }
+}
+
+impl<'a, 'h, T, F, O, E> PartialPending<'a, T, F, O, E>
+    for Prepared<Edit<'h, T>, T, F, E, mode::Recorded>
+where
+    Self: PartialApply<T, F, O, E, Next = T>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, next: &'a T) -> &'a T {
+        next
+    }
+}
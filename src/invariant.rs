@@ -0,0 +1,142 @@
+use crate::prepared::mode;
+use crate::{target, OneMut, PartialApply, PartialPending, Prepared, Take, Token};
+
+/// Indicates that a value of `T` is in a broken state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantError {
+    pub reason: String,
+}
+
+impl InvariantError {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// The documented invariants of `T`.
+///
+/// Checking is opt-in: a plain `prepare()` into a `T: Invariant` is not
+/// checked. `prepare_checked()` checks a single appliance, and once
+/// registered by `OneMut::with_invariant()`, every `prepare()` into `T`
+/// checks the modified copy of `T` before it's replaced into the
+/// original `T`, whichever closure mutated it.
+///
+/// The combinators that would modify the copy outside of the checked
+/// closure only accept the default mode, so a registered `OneMut` can't
+/// be the later member of an `and_then()`:
+///
+/// ```compile_fail
+/// # use onemut::{Apply, Invariant, InvariantError, OneMut};
+/// # #[derive(Clone)]
+/// # struct A(pub u8);
+/// # impl Invariant for A {
+/// #     fn check(&self) -> Result<(), InvariantError> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let mut a = A(0);
+/// let mut b = A(0);
+/// let amut = OneMut::new(&mut a);
+/// let bmut = OneMut::new(&mut b).with_invariant();
+/// let _res = amut
+///     .unchecked_prepare(|_a: &mut A| Ok::<_, InvariantError>(()))
+///     .and_then(bmut, |_o: &(), _b: &mut A| Ok(()))
+///     .apply();
+/// ```
+///
+/// Nor can it have an `or_else()` fallback:
+///
+/// ```compile_fail
+/// # use onemut::{Apply, Invariant, InvariantError, OneMut};
+/// # #[derive(Clone)]
+/// # struct A(pub u8);
+/// # impl Invariant for A {
+/// #     fn check(&self) -> Result<(), InvariantError> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let mut a = A(0);
+/// let amut = OneMut::new(&mut a).with_invariant();
+/// let _res = amut
+///     .unchecked_prepare(|_a: &mut A| Ok::<_, InvariantError>(()))
+///     .or_else(|_e: InvariantError, _a: &mut A| Ok::<_, InvariantError>(()))
+///     .apply();
+/// ```
+pub trait Invariant {
+    fn check(&self) -> Result<(), InvariantError>;
+}
+
+impl<'t, T> OneMut<'t, T>
+where
+    T: Invariant,
+{
+    /// Registers `T`'s `Invariant`, so that all of the following
+    /// `prepare()`s check it, as `prepare_checked()` does.
+    pub fn with_invariant(self) -> OneMut<'t, T, mode::Checked> {
+        self.with_mode()
+    }
+}
+
+impl<'t, T> OneMut<'t, T> {
+    /// Defines how `T` should be mutated, given an `Ok` response, while
+    /// requiring that the modified `T` upholds it's `Invariant`.
+    ///
+    /// If the check fails, the appliance fails with
+    /// `E::from(InvariantError)`, giving back the `Token`.
+    ///
+    /// This checks a single appliance, see also
+    /// `OneMut::with_invariant()`.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_checked<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Checked> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `OneMut::prepare_checked()`.
+    pub fn unchecked_prepare_checked<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Checked> {
+        Prepared::with_mode(self, f)
+    }
+}
+
+impl<'t, OuterT, T, F, O, E> PartialApply<T, F, O, E> for Prepared<OuterT, T, F, E, mode::Checked>
+where
+    OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
+    F: FnOnce(&mut T) -> Result<O, E>,
+    T: 't + Clone + Invariant,
+    E: From<InvariantError>,
+    OuterT: 't,
+{
+    type Next = T;
+
+    fn get_next(&self) -> T {
+        let next: &T = self.inner.take_ref();
+        next.clone()
+    }
+
+    fn modify_next(&self, mut next: T, f: F) -> Result<(O, T), E> {
+        let o = (f)(&mut next)?;
+        next.check()?;
+        Ok((o, next))
+    }
+
+    fn replace(&mut self, next: T) {
+        let current: &mut T = self.inner.take_mut();
+        *current = next;
+    }
+}
+
+impl<'a, OuterT, T, F, O, E> PartialPending<'a, T, F, O, E>
+    for Prepared<OuterT, T, F, E, mode::Checked>
+where
+    Self: PartialApply<T, F, O, E, Next = T>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, next: &'a T) -> &'a T {
+        next
+    }
+}
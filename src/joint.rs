@@ -0,0 +1,165 @@
+use crate::prepared::mode;
+use crate::{target, OneMut, PartialApply, PartialPending, Prepared, Take, TakeOwned, Token};
+use paste::paste;
+use std::marker::PhantomData;
+
+/// A tuple of `OneMut`s that get modified together by a single closure.
+///
+/// See `prepare_joint()`.
+pub trait Joint {
+    /// The tuple of the protected types.
+    type Values;
+}
+
+/// Defines how all `T`s should be mutated, given an `Ok` response, by a
+/// single closure that has mut access into all of the copies at once.
+///
+/// The result is a single `Prepared` modification for the tuple of the
+/// `T`s, which can be chained with other `Prepared` modifications, and
+/// which gives a single `ConsumedToken` for the tuple of the `T`s.
+///
+/// # Safety
+///
+/// See `OneMut::prepare()`.
+pub unsafe fn prepare_joint<J: Joint, F, E>(
+    ones: J,
+    f: F,
+) -> Prepared<J, J::Values, F, E, mode::Joint> {
+    Prepared::with_mode(ones, f)
+}
+
+/// See `prepare_joint()`.
+pub fn unchecked_prepare_joint<J: Joint, F, E>(
+    ones: J,
+    f: F,
+) -> Prepared<J, J::Values, F, E, mode::Joint> {
+    Prepared::with_mode(ones, f)
+}
+
+macro_rules! joint_impls {
+    ( $( $n:tt ),+ ) => {
+        paste! {
+            impl<
+                $( [<'t $n>], )+
+                $( [<T $n>], )+
+            > Joint for (
+                $( OneMut<[<'t $n>], [<T $n>]>, )+
+            ) {
+                type Values = (
+                    $( [<T $n>], )+
+                );
+            }
+
+            impl<
+                'tall,
+                $( [<'t $n>], )+
+                $( [<T $n>], )+
+            > TakeOwned<
+                Token<'tall, ( $( [<T $n>], )+ )>,
+                target::Token
+            > for (
+                $( OneMut<[<'t $n>], [<T $n>]>, )+
+            ) {
+                /// # Safety
+                ///
+                /// It is assumed that the caller has correctly used this method.
+                unsafe fn take_owned(self) -> Token<'tall, ( $( [<T $n>], )+ )> {
+                    let (
+                        $( [<o $n>], )+
+                    ) = self;
+                    $( let _token = [<o $n>].unchecked_token(); )+
+                    Token(PhantomData)
+                }
+            }
+
+            impl<
+                $( [<'t $n>], )+
+                $( [<T $n>], )+
+                F,
+                O,
+                E,
+            > PartialApply<( $( [<T $n>], )+ ), F, O, E>
+                for Prepared<
+                    ( $( OneMut<[<'t $n>], [<T $n>]>, )+ ),
+                    ( $( [<T $n>], )+ ),
+                    F,
+                    E,
+                    mode::Joint,
+                >
+            where
+                F: for<'a> FnOnce(( $( &'a mut [<T $n>], )+ )) -> Result<O, E>,
+                $( [<T $n>]: Clone, )+
+            {
+                type Next = ( $( [<T $n>], )+ );
+
+                fn get_next(&self) -> Self::Next {
+                    let (
+                        $( [<o $n>], )+
+                    ) = &self.inner;
+                    (
+                        $( [<o $n>].as_ref().clone(), )+
+                    )
+                }
+
+                fn modify_next(&self, mut next: Self::Next, f: F) -> Result<(O, Self::Next), E> {
+                    let (
+                        $( [<n $n>], )+
+                    ) = &mut next;
+                    let o = (f)(( $( [<n $n>], )+ ))?;
+                    Ok((o, next))
+                }
+
+                fn replace(&mut self, next: Self::Next) {
+                    let (
+                        $( [<o $n>], )+
+                    ) = &mut self.inner;
+                    let (
+                        $( [<n $n>], )+
+                    ) = next;
+                    $(
+                        let [<c $n>]: &mut [<T $n>] = [<o $n>].take_mut();
+                        *[<c $n>] = [<n $n>];
+                    )+
+                }
+            }
+
+            impl<
+                'a,
+                $( [<'t $n>], )+
+                $( [<T $n>], )+
+                F,
+                O,
+                E,
+            > PartialPending<'a, ( $( [<T $n>], )+ ), F, O, E>
+                for Prepared<
+                    ( $( OneMut<[<'t $n>], [<T $n>]>, )+ ),
+                    ( $( [<T $n>], )+ ),
+                    F,
+                    E,
+                    mode::Joint,
+                >
+            where
+                Self: PartialApply<( $( [<T $n>], )+ ), F, O, E, Next = ( $( [<T $n>], )+ )>,
+            {
+                type View = &'a ( $( [<T $n>], )+ );
+
+                fn pending(&'a self, next: &'a Self::Next) -> Self::View {
+                    next
+                }
+            }
+        }
+    };
+}
+
+joint_impls! {1}
+joint_impls! {1, 2}
+joint_impls! {1, 2, 3}
+joint_impls! {1, 2, 3, 4}
+joint_impls! {1, 2, 3, 4, 5}
+joint_impls! {1, 2, 3, 4, 5, 6}
+joint_impls! {1, 2, 3, 4, 5, 6, 7}
+joint_impls! {1, 2, 3, 4, 5, 6, 7, 8}
+joint_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9}
+joint_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10}
+joint_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11}
+joint_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12}
@@ -1,20 +1,62 @@
+// the upstream docs use unindented list continuations
+#![allow(clippy::doc_lazy_continuation)]
+
 pub mod macros;
 pub mod split;
 
 pub mod access;
+pub mod and_then;
+pub mod atomic;
+pub mod batch;
+pub mod cell;
 pub mod chain;
+pub mod change;
+pub mod cow;
+pub mod either;
+pub mod history;
+pub mod invariant;
+pub mod joint;
+pub mod lock;
+pub mod overlay;
+#[cfg(feature = "persistent")]
+pub mod persistent;
 pub mod prepared;
+pub mod retry;
+pub mod savepoint;
+pub mod scratch;
+pub mod stm;
 pub mod token;
+pub mod transaction;
+pub mod undo;
+pub mod versioned;
 
 pub use access::{target, Take, TakeOwned};
-pub use chain::Chain;
+pub use and_then::{AndThen, PartialPending, Pending};
+pub use atomic::AtomicShared;
+pub use batch::{batch, batch_array};
+pub use chain::{Chain, CheckedChain};
+pub use change::{Change, ContentHash, Unchanged};
+pub use either::{Branch, Either, OrElse};
+pub use history::{Edit, History, HistoryError};
+pub use invariant::{Invariant, InvariantError};
+pub use lock::{lock_all, try_lock_all, LockError};
+pub use onemut_derive::Overlaid;
+pub use overlay::Overlaid;
+#[cfg(feature = "persistent")]
+pub use persistent::{PMap, PVector};
 pub use prepared::Prepared;
-pub use token::{ConsumedToken, Token, UpgraderToken};
+pub use retry::{AllErrors, Backoff, Classify, Retry, RetryPolicy, Retrying};
+pub use savepoint::{nested, Savepoint};
+pub use scratch::{ApplyScratch, PartialScratch, Scratch};
+pub use token::{ConsumedToken, ConsumedTokenSet, Token, TokenKey, TokenSet, UpgraderToken};
+pub use transaction::{DynApply, DynTransaction};
+pub use undo::{ApplyReversible, PartialSwap, Undo};
+pub use versioned::{Conflict, Optimistic, RepeatedCellError, Versioned};
 
 pub mod from_apply {
     pub use crate::split::{
         FromApply1, FromApply10, FromApply11, FromApply12, FromApply2, FromApply3, FromApply4,
-        FromApply5, FromApply6, FromApply7, FromApply8, FromApply9,
+        FromApply5, FromApply6, FromApply7, FromApply8, FromApply9, OneMuts,
     };
 }
 
@@ -36,13 +78,18 @@ pub mod from_apply {
 /// - If the appliance fails, `T` never get's changed and only the
 /// `Token` is returned. This means that further accesses are
 /// disallowed and the `Token` can prove that `T` stayed unchanged.
-pub struct OneMut<'t, T> {
+///
+/// `M` is the `prepared::mode` of `prepare()`. It's only other than
+/// `mode::Copied` if `T`'s invariant got registered, see
+/// `OneMut::with_invariant()`.
+pub struct OneMut<'t, T, M = prepared::mode::Copied> {
     inner: &'t mut T,
     token: Token<'t, T>,
+    _mode: std::marker::PhantomData<M>,
 }
 
 /// Allows shared access into the Token.
-impl<'t, T> AsRef<T> for OneMut<'t, T> {
+impl<'t, T, M> AsRef<T> for OneMut<'t, T, M> {
     fn as_ref(&self) -> &T {
         self.inner
     }
@@ -51,7 +98,22 @@ impl<'t, T> AsRef<T> for OneMut<'t, T> {
 impl<'t, T> OneMut<'t, T> {
     pub fn new(inner: &'t mut T) -> Self {
         let (token, inner) = Token::new(inner);
-        Self { inner, token }
+        Self {
+            inner,
+            token,
+            _mode: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'t, T, M> OneMut<'t, T, M> {
+    /// Changes the mode of `prepare()`.
+    pub(crate) fn with_mode<M2>(self) -> OneMut<'t, T, M2> {
+        OneMut {
+            inner: self.inner,
+            token: self.token,
+            _mode: std::marker::PhantomData,
+        }
     }
 
     /// Defines how `T` should be mutated, given an `Ok` response.
@@ -78,20 +140,20 @@ impl<'t, T> OneMut<'t, T> {
     /// have been returned, and ensuring that you don't early-return an `Ok`
     /// before finalizing all of your necessary mutations.  
     /// Otherwise you'll have inconsistent internal state.
-    pub unsafe fn prepare<F, E>(self, f: F) -> Prepared<OneMut<'t, T>, T, F, E> {
-        Prepared::new(self, f)
+    pub unsafe fn prepare<F, E>(self, f: F) -> Prepared<Self, T, F, E, M> {
+        Prepared::with_mode(self, f)
     }
 
-    pub fn unchecked_prepare<F, E>(self, f: F) -> Prepared<OneMut<'t, T>, T, F, E> {
-        Prepared::new(self, f)
+    pub fn unchecked_prepare<F, E>(self, f: F) -> Prepared<Self, T, F, E, M> {
+        Prepared::with_mode(self, f)
     }
 
     /// Skips changing `T` by using an `|_| Ok(())` on `prepare()`.  
     ///
     /// This may be useful for easily chaining `Prepared` values.
     #[allow(clippy::type_complexity)]
-    pub fn unchecked_skip<E>(self) -> Prepared<OneMut<'t, T>, T, fn(&mut T) -> Result<(), E>, E> {
-        Prepared::new(self, |_t| Ok(()))
+    pub fn unchecked_skip<E>(self) -> Prepared<Self, T, fn(&mut T) -> Result<(), E>, E, M> {
+        Prepared::with_mode(self, |_t| Ok(()))
     }
 
     /// Skips changing `T` by using an `|_| Ok(())` on `prepare()`.  
@@ -104,7 +166,7 @@ impl<'t, T> OneMut<'t, T> {
     /// You must guarantee that this value being skipped of mutation is
     /// logically correct.
     #[allow(clippy::type_complexity)]
-    pub unsafe fn skip<E>(self) -> Prepared<OneMut<'t, T>, T, fn(&mut T) -> Result<(), E>, E> {
+    pub unsafe fn skip<E>(self) -> Prepared<Self, T, fn(&mut T) -> Result<(), E>, E, M> {
         self.unchecked_skip()
     }
 
@@ -183,13 +245,34 @@ impl<T, E> ResultLike for Result<T, E> {
 /// - `F` is the scoped closure that will mut access `T`.
 /// - `O` is `F`'s `Ok` return type.
 /// - `E` is `F`'s `Err` return type.
+///
+/// # Changes in 0.2
+///
+/// The working copy became the `Next` type, and `modify_next()` now
+/// borrows `&self`. Modes such as `prepared::mode::Lazy` and
+/// `prepared::mode::Compared` need a working copy that is not a `T`, and
+/// need to read the original `T` during the modification, which the
+/// previous `fn modify_next(next: T, f: F)` couldn't express. An extension
+/// trait with a blanket impl over the previous one would overlap with the
+/// per-mode impls, so the trait itself was changed.
+///
+/// Implementations from 0.1 are migrated by adding `type Next = T;` and a
+/// `&self` receiver to `modify_next()`.
 pub trait PartialApply<T, F, O, E> {
+    /// The working copy of `T`.
+    ///
+    /// This is usually `T` itself, but it may be a lazy copy, such as
+    /// an `Option<T>` that is only filled in on the first mut access.
+    type Next;
+
     /// Creates a copy of `T`.
-    fn get_next(&self) -> T;
+    fn get_next(&self) -> Self::Next;
     /// Applies a modification into a `T` (presumably the copy of `T`).
-    fn modify_next(next: T, f: F) -> Result<(O, T), E>;
+    ///
+    /// The original `T` may be read, but must not be changed.
+    fn modify_next(&self, next: Self::Next, f: F) -> Result<(O, Self::Next), E>;
     /// Replaces the original `T` with the modified copy of `T`.
-    fn replace(&mut self, next: T);
+    fn replace(&mut self, next: Self::Next);
 }
 
 /// # Safety
@@ -0,0 +1,264 @@
+use paste::paste;
+use std::ops::DerefMut;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard, TryLockError};
+use std::time::{Duration, Instant};
+
+/// A lock that gives out exclusive access into it's `Target`.
+///
+/// Poisoned locks are not acquired, as another thread panicked while
+/// holding the guard, and may have left the `Target` partially modified.
+/// To acquire it anyway, the poisoning must first be explicitly cleared,
+/// such as by `Mutex::clear_poison()`.
+/// This is the opposite of `Versioned`, whose `T` can't be left partially
+/// modified, and so which recovers it's poisoned lock.
+pub trait Lockable<'a> {
+    type Target: 'a;
+    type Guard: DerefMut<Target = Self::Target> + 'a;
+
+    /// Identifies the lock itself, which decides the locking order.
+    ///
+    /// Handles into the same lock, such as clones of an `Arc<Mutex<T>>`,
+    /// must have the same id.
+    fn id(&self) -> usize;
+
+    /// Blocks until the exclusive access is acquired.
+    fn lock(&'a self) -> Result<Self::Guard, LockError>;
+
+    /// Tries to acquire the exclusive access without blocking.
+    ///
+    /// Returns `Ok(None)` if the lock is busy.
+    fn try_lock(&'a self) -> Result<Option<Self::Guard>, LockError>;
+}
+
+impl<'a, T: 'a> Lockable<'a> for Mutex<T> {
+    type Target = T;
+    type Guard = MutexGuard<'a, T>;
+
+    fn id(&self) -> usize {
+        address(self)
+    }
+
+    fn lock(&'a self) -> Result<Self::Guard, LockError> {
+        Mutex::lock(self).map_err(|_| LockError::Poisoned)
+    }
+
+    fn try_lock(&'a self) -> Result<Option<Self::Guard>, LockError> {
+        match Mutex::try_lock(self) {
+            Ok(guard) => Ok(Some(guard)),
+            Err(TryLockError::Poisoned(_)) => Err(LockError::Poisoned),
+            Err(TryLockError::WouldBlock) => Ok(None),
+        }
+    }
+}
+
+impl<'a, T: 'a> Lockable<'a> for RwLock<T> {
+    type Target = T;
+    type Guard = RwLockWriteGuard<'a, T>;
+
+    fn id(&self) -> usize {
+        address(self)
+    }
+
+    fn lock(&'a self) -> Result<Self::Guard, LockError> {
+        RwLock::write(self).map_err(|_| LockError::Poisoned)
+    }
+
+    fn try_lock(&'a self) -> Result<Option<Self::Guard>, LockError> {
+        match RwLock::try_write(self) {
+            Ok(guard) => Ok(Some(guard)),
+            Err(TryLockError::Poisoned(_)) => Err(LockError::Poisoned),
+            Err(TryLockError::WouldBlock) => Ok(None),
+        }
+    }
+}
+
+impl<'a, L: Lockable<'a>> Lockable<'a> for Arc<L> {
+    type Target = L::Target;
+    type Guard = L::Guard;
+
+    /// The id of the shared lock, not of the handle.
+    fn id(&self) -> usize {
+        L::id(self)
+    }
+
+    fn lock(&'a self) -> Result<Self::Guard, LockError> {
+        L::lock(self)
+    }
+
+    fn try_lock(&'a self) -> Result<Option<Self::Guard>, LockError> {
+        L::try_lock(self)
+    }
+}
+
+/// Indicates why not all locks could be acquired.
+///
+/// In either case, all of the already acquired locks are released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockError {
+    /// Some lock was poisoned.
+    Poisoned,
+    /// Not all locks could be acquired in time.
+    Timeout,
+    /// The same lock is present more than once, at the `index` tuple
+    /// position, and so it could never be acquired.
+    Repeated { index: usize },
+}
+
+/// Acquires a tuple of locks, always in the order of their ids.
+///
+/// As long as every multi-lock acquisition goes through `LockAll`,
+/// no two threads can wait on each other's locks, so no deadlocks
+/// can happen.
+///
+/// The returned guards can be turned into `OneMut`s with
+/// `from_apply::OneMuts`.
+///
+/// Fails with `LockError::Repeated`, before acquiring any lock, if the
+/// same lock is present more than once.
+pub trait LockAll<'a> {
+    type Guards;
+
+    /// Blocks until all locks are acquired.
+    fn lock_all(self) -> Result<Self::Guards, LockError>;
+
+    /// Tries to acquire all locks until `timeout` has passed.
+    ///
+    /// Whenever some lock is busy, all of the already acquired locks
+    /// are released, and the whole acquisition is retried after a
+    /// backoff.
+    fn try_lock_all(self, timeout: Duration) -> Result<Self::Guards, LockError>;
+}
+
+/// Acquires a tuple of locks, always in the order of their ids.
+///
+/// See `LockAll`.
+pub fn lock_all<'a, L: LockAll<'a>>(locks: L) -> Result<L::Guards, LockError> {
+    locks.lock_all()
+}
+
+/// Tries to acquire a tuple of locks until `timeout` has passed.
+///
+/// See `LockAll`.
+pub fn try_lock_all<'a, L: LockAll<'a>>(
+    locks: L,
+    timeout: Duration,
+) -> Result<L::Guards, LockError> {
+    locks.try_lock_all(timeout)
+}
+
+const BACKOFF_START: Duration = Duration::from_micros(1);
+const BACKOFF_MAX: Duration = Duration::from_millis(1);
+
+fn address<T>(t: &T) -> usize {
+    t as *const T as usize
+}
+
+/// Sorts the `(id, index)` pairs, and checks for repeated ids.
+fn lock_order(order: &mut [(usize, usize)]) -> Result<(), LockError> {
+    order.sort_unstable();
+    match order.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        // the tuple positions start at `1`
+        Some(pair) => Err(LockError::Repeated {
+            index: pair[1].1 - 1,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Waits before retrying an acquisition, or indicates that the
+/// `deadline` was reached.
+fn backoff(backoff: &mut Duration, deadline: Instant) -> Result<(), LockError> {
+    let now = Instant::now();
+    if now >= deadline {
+        return Err(LockError::Timeout);
+    }
+    std::thread::sleep(std::cmp::min(*backoff, deadline - now));
+    *backoff = std::cmp::min(*backoff * 2, BACKOFF_MAX);
+    Ok(())
+}
+
+macro_rules! lock_all_impls {
+    ( $( $n:tt ),+ ) => {
+        paste! {
+            impl<
+                'a,
+                $( [<L $n>]: Lockable<'a>, )+
+            > LockAll<'a> for (
+                $( &'a [<L $n>], )+
+            ) {
+                type Guards = (
+                    $( [<L $n>]::Guard, )+
+                );
+
+                fn lock_all(self) -> Result<Self::Guards, LockError> {
+                    let (
+                        $( [<l $n>], )+
+                    ) = self;
+                    let mut order = [
+                        $( ([<l $n>].id(), $n), )+
+                    ];
+                    lock_order(&mut order)?;
+
+                    $( let mut [<g $n>] = None; )+
+                    for &(_id, n) in order.iter() {
+                        match n {
+                            // on errors, the acquired guards get dropped
+                            $( $n => [<g $n>] = Some([<l $n>].lock()?), )+
+                            _ => unreachable!(),
+                        }
+                    }
+                    Ok((
+                        $( [<g $n>].unwrap(), )+
+                    ))
+                }
+
+                fn try_lock_all(self, timeout: Duration) -> Result<Self::Guards, LockError> {
+                    let deadline = Instant::now() + timeout;
+                    let (
+                        $( [<l $n>], )+
+                    ) = self;
+                    let mut order = [
+                        $( ([<l $n>].id(), $n), )+
+                    ];
+                    lock_order(&mut order)?;
+
+                    let mut wait = BACKOFF_START;
+                    'retry: loop {
+                        $( let mut [<g $n>] = None; )+
+                        for &(_id, n) in order.iter() {
+                            let acquired = match n {
+                                $( $n => {
+                                    [<g $n>] = [<l $n>].try_lock()?;
+                                    [<g $n>].is_some()
+                                }, )+
+                                _ => unreachable!(),
+                            };
+                            if !acquired {
+                                // releases the already acquired locks
+                                $( drop([<g $n>]); )+
+                                backoff(&mut wait, deadline)?;
+                                continue 'retry;
+                            }
+                        }
+                        return Ok((
+                            $( [<g $n>].unwrap(), )+
+                        ));
+                    }
+                }
+            }
+        }
+    };
+}
+
+lock_all_impls! {1}
+lock_all_impls! {1, 2}
+lock_all_impls! {1, 2, 3}
+lock_all_impls! {1, 2, 3, 4}
+lock_all_impls! {1, 2, 3, 4, 5}
+lock_all_impls! {1, 2, 3, 4, 5, 6}
+lock_all_impls! {1, 2, 3, 4, 5, 6, 7}
+lock_all_impls! {1, 2, 3, 4, 5, 6, 7, 8}
+lock_all_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9}
+lock_all_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10}
+lock_all_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11}
+lock_all_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12}
@@ -0,0 +1,142 @@
+use crate::prepared::mode;
+use crate::{target, OneMut, PartialApply, Prepared, Take, Token};
+
+/// A struct whose fields can be lazily copied into an overlay.
+///
+/// This is implemented by `#[derive(Overlaid)]`, which also defines the
+/// `Overlay` and `Dirty` types, and which the `overlay!` macro expands to.
+///
+/// ```
+/// use onemut::{Apply, Overlaid};
+///
+/// #[derive(Clone, Debug, Overlaid)]
+/// pub struct State {
+///     pub a: Vec<u8>,
+///     pub b: String,
+/// }
+///
+/// let mut state = State { a: vec![0], b: String::new() };
+/// let amut = onemut::OneMut::new(&mut state);
+/// let prep = amut.unchecked_prepare_overlay(|s: &mut StateOverlay| {
+///     s.a_mut().push(1);
+///     Ok::<_, ()>(s.b().len())
+/// });
+/// let (_len, _tok) = prep.apply().unwrap();
+/// assert_eq!(state.a, vec![0, 1]);
+/// ```
+///
+/// A field can't have the same name as a method of the overlay:
+///
+/// ```compile_fail
+/// #[derive(Clone, onemut::Overlaid)]
+/// pub struct State {
+///     pub a: Vec<u8>,
+///     // collides with the mut access into `a`
+///     pub a_mut: Vec<u8>,
+/// }
+/// ```
+pub trait Overlaid {
+    /// The fields that got mutably accessed, as `Option`s of copies.
+    type Dirty: Default;
+
+    /// Field-wise clone-on-write access into the struct.
+    ///
+    /// Reading a field reads from the original struct, and the first mut
+    /// access into a field clones it into the `Dirty` fields.
+    type Overlay<'a>
+    where
+        Self: 'a;
+
+    fn overlay(&self, dirty: Self::Dirty) -> Self::Overlay<'_>;
+    fn into_dirty(overlay: Self::Overlay<'_>) -> Self::Dirty;
+    /// Replaces only the fields that are dirty.
+    fn write_back(&mut self, dirty: Self::Dirty);
+}
+
+/// Defines a struct with `#[derive(Overlaid)]`, for when the struct's
+/// definition should stay in a `macro_rules!`.
+///
+/// The struct's overlay type gets the `Overlay` suffix, see
+/// `derive(Overlaid)` for it's methods. The derive gets the path to this
+/// crate, so it also works when `onemut` is renamed.
+///
+/// Generic structs are not supported.
+///
+/// ```
+/// use onemut::Apply;
+///
+/// onemut::overlay! {
+///     #[derive(Clone, Debug)]
+///     pub struct State {
+///         pub a: Vec<u8>,
+///         pub b: String,
+///     }
+/// }
+///
+/// let mut state = State { a: vec![0], b: String::new() };
+/// let amut = onemut::OneMut::new(&mut state);
+/// let prep = amut.unchecked_prepare_overlay(|s: &mut StateOverlay| {
+///     s.a_mut().push(1);
+///     Ok::<_, ()>(s.b().len())
+/// });
+/// let (_len, _tok) = prep.apply().unwrap();
+/// assert_eq!(state.a, vec![0, 1]);
+/// ```
+#[macro_export]
+macro_rules! overlay {
+    ($($item:tt)*) => {
+        #[derive($crate::Overlaid)]
+        #[onemut(crate = $crate)]
+        $($item)*
+    };
+}
+
+impl<'t, T> OneMut<'t, T> {
+    /// Defines how `T` should be mutated, given an `Ok` response, without
+    /// cloning the fields of `T` unless they're mutably accessed.
+    ///
+    /// The closure receives the overlay of `T` (see `overlay!`), and the
+    /// appliance only replaces the fields that got mutably accessed.
+    /// The `Token` still represents the whole `T`.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare_overlay<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Overlay> {
+        Prepared::with_mode(self, f)
+    }
+
+    /// See `OneMut::prepare_overlay()`.
+    pub fn unchecked_prepare_overlay<F, E>(self, f: F) -> Prepared<Self, T, F, E, mode::Overlay> {
+        Prepared::with_mode(self, f)
+    }
+}
+
+impl<'t, OuterT, T, F, O, E> PartialApply<T, F, O, E> for Prepared<OuterT, T, F, E, mode::Overlay>
+where
+    OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
+    F: for<'a> FnOnce(&mut T::Overlay<'a>) -> Result<O, E>,
+    T: 't + Overlaid,
+    OuterT: 't,
+{
+    /// The copies of the fields that got mutably accessed.
+    type Next = T::Dirty;
+
+    /// Doesn't clone any field.
+    fn get_next(&self) -> T::Dirty {
+        T::Dirty::default()
+    }
+
+    fn modify_next(&self, next: T::Dirty, f: F) -> Result<(O, T::Dirty), E> {
+        let original: &T = self.inner.take_ref();
+        let mut next = original.overlay(next);
+        let o = (f)(&mut next)?;
+        Ok((o, T::into_dirty(next)))
+    }
+
+    /// Only replaces the fields that got mutably accessed.
+    fn replace(&mut self, next: T::Dirty) {
+        let current: &mut T = self.inner.take_mut();
+        current.write_back(next);
+    }
+}
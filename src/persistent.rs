@@ -0,0 +1,781 @@
+//! Persistent collections, which share their structure between clones.
+//!
+//! Cloning a collection only clones the `Arc` of it's root, so a
+//! `Prepared::get_next()` copy is `O(1)`. Modifying a copy only clones
+//! the nodes on the path into the modified element, and the appliance
+//! only swaps the root of the original.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+/// A persistent vector, as a 32-way trie.
+///
+/// `get`, `get_mut`, `set`, `push` and `pop` are `O(log32 n)`.
+///
+/// `insert` and `remove` are `O(n - index)`: without relaxed (RRB)
+/// nodes, every element after `index` has to be shifted, so only the
+/// paths before `index` stay shared with the original.
+pub struct PVector<T> {
+    root: Arc<VNode<T>>,
+    len: usize,
+    /// How many bits the root level takes from an index.
+    shift: usize,
+}
+
+enum VNode<T> {
+    Branch(Vec<Arc<VNode<T>>>),
+    Leaf(Vec<T>),
+}
+
+impl<T: Clone> Clone for VNode<T> {
+    fn clone(&self) -> Self {
+        match self {
+            VNode::Branch(children) => VNode::Branch(children.clone()),
+            VNode::Leaf(values) => VNode::Leaf(values.clone()),
+        }
+    }
+}
+
+impl<T> VNode<T> {
+    fn empty(level: usize) -> Self {
+        if level == 0 {
+            VNode::Leaf(Vec::with_capacity(WIDTH))
+        } else {
+            VNode::Branch(Vec::with_capacity(WIDTH))
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            VNode::Branch(children) => children.is_empty(),
+            VNode::Leaf(values) => values.is_empty(),
+        }
+    }
+}
+
+impl<T: Clone> VNode<T> {
+    fn push(&mut self, level: usize, index: usize, value: T) {
+        match self {
+            VNode::Leaf(values) => values.push(value),
+            VNode::Branch(children) => {
+                let i = (index >> level) & MASK;
+                if i == children.len() {
+                    children.push(Arc::new(VNode::empty(level - BITS)));
+                }
+                Arc::make_mut(&mut children[i]).push(level - BITS, index, value)
+            }
+        }
+    }
+
+    fn pop(&mut self, level: usize, index: usize) -> Option<T> {
+        match self {
+            VNode::Leaf(values) => values.pop(),
+            VNode::Branch(children) => {
+                let i = (index >> level) & MASK;
+                let child = Arc::make_mut(&mut children[i]);
+                let value = child.pop(level - BITS, index);
+                if child.is_empty() {
+                    let _empty = children.pop();
+                }
+                value
+            }
+        }
+    }
+
+    fn get_mut(&mut self, level: usize, index: usize) -> &mut T {
+        match self {
+            VNode::Leaf(values) => &mut values[index & MASK],
+            VNode::Branch(children) => {
+                let i = (index >> level) & MASK;
+                Arc::make_mut(&mut children[i]).get_mut(level - BITS, index)
+            }
+        }
+    }
+}
+
+impl<T> PVector<T> {
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(VNode::empty(0)),
+            len: 0,
+            shift: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether both vectors share the same root, ie. whether one is an
+    /// unmodified clone of the other.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.root, &other.root)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let mut node = &*self.root;
+        let mut level = self.shift;
+        loop {
+            match node {
+                VNode::Branch(children) => {
+                    node = &children[(index >> level) & MASK];
+                    level -= BITS;
+                }
+                VNode::Leaf(values) => return Some(&values[index & MASK]),
+            }
+        }
+    }
+
+    pub fn iter(&self) -> PVectorIter<'_, T> {
+        PVectorIter {
+            vector: self,
+            index: 0,
+        }
+    }
+}
+
+impl<T: Clone> PVector<T> {
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let shift = self.shift;
+        Some(Arc::make_mut(&mut self.root).get_mut(shift, index))
+    }
+
+    /// Replaces the element at `index`, returning the old one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) -> T {
+        let len = self.len;
+        let current = self
+            .get_mut(index)
+            .unwrap_or_else(|| panic!("index {} out of bounds for length {}", index, len));
+        std::mem::replace(current, value)
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == WIDTH << self.shift {
+            // the trie is full, so it gets a new root level
+            let old = std::mem::replace(&mut self.root, Arc::new(VNode::Branch(Vec::new())));
+            if let VNode::Branch(children) = Arc::make_mut(&mut self.root) {
+                children.push(old);
+            }
+            self.shift += BITS;
+        }
+        let (shift, index) = (self.shift, self.len);
+        Arc::make_mut(&mut self.root).push(shift, index, value);
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let (shift, index) = (self.shift, self.len - 1);
+        let value = Arc::make_mut(&mut self.root).pop(shift, index);
+        self.len -= 1;
+
+        // removes the root levels that only have a single child
+        while self.shift > 0 {
+            let only_child = match &*self.root {
+                VNode::Branch(children) if children.len() == 1 => Arc::clone(&children[0]),
+                _ => break,
+            };
+            self.root = only_child;
+            self.shift -= BITS;
+        }
+        value
+    }
+
+    /// Inserts `value` at `index`, shifting the later elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len;
+        assert!(
+            index <= len,
+            "index {} out of bounds for length {}",
+            index,
+            len
+        );
+        let tail = self.split_tail(index);
+        self.push(value);
+        self.extend(tail.into_iter().rev());
+    }
+
+    /// Removes the element at `index`, shifting the later elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(
+            index < len,
+            "index {} out of bounds for length {}",
+            index,
+            len
+        );
+        let mut tail = self.split_tail(index);
+        let removed = tail.pop().expect("the tail has the element at index");
+        self.extend(tail.into_iter().rev());
+        removed
+    }
+
+    /// Pops the elements from `index` onwards, in reverse order.
+    fn split_tail(&mut self, index: usize) -> Vec<T> {
+        let mut tail = Vec::with_capacity(self.len - index);
+        while self.len > index {
+            tail.extend(self.pop());
+        }
+        tail
+    }
+}
+
+impl<T> Clone for PVector<T> {
+    /// Only clones the root `Arc`.
+    fn clone(&self) -> Self {
+        Self {
+            root: Arc::clone(&self.root),
+            len: self.len,
+            shift: self.shift,
+        }
+    }
+}
+
+impl<T> Default for PVector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for PVector<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && (self.ptr_eq(other) || self.iter().eq(other.iter()))
+    }
+}
+
+impl<T: Eq> Eq for PVector<T> {}
+
+impl<T: Clone> Extend<T> for PVector<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PVector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vector = Self::new();
+        vector.extend(iter);
+        vector
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PVector<T> {
+    type Item = &'a T;
+    type IntoIter = PVectorIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct PVectorIter<'a, T> {
+    vector: &'a PVector<T>,
+    index: usize,
+}
+
+impl<'a, T> Iterator for PVectorIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let value = self.vector.get(self.index)?;
+        self.index += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vector.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+/// How many bits a hash has.
+const HASH_BITS: usize = 64;
+
+/// A persistent hash map, as a hash array mapped trie.
+///
+/// `get`, `get_mut`, `insert` and `remove` are `O(log32 n)`.
+pub struct PMap<K, V, S = RandomState> {
+    root: Arc<HNode<K, V>>,
+    len: usize,
+    hasher: S,
+}
+
+enum HNode<K, V> {
+    /// Only has the entries that have a bit set in the `bitmap`.
+    Branch {
+        bitmap: u32,
+        entries: Vec<HEntry<K, V>>,
+    },
+    /// Has only `Leaf` entries, all with the same hash.
+    Collision(Vec<HEntry<K, V>>),
+}
+
+enum HEntry<K, V> {
+    Leaf(u64, K, V),
+    Node(Arc<HNode<K, V>>),
+}
+
+impl<K: Clone, V: Clone> Clone for HNode<K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            HNode::Branch { bitmap, entries } => HNode::Branch {
+                bitmap: *bitmap,
+                entries: entries.clone(),
+            },
+            HNode::Collision(entries) => HNode::Collision(entries.clone()),
+        }
+    }
+}
+
+impl<K: Clone, V: Clone> Clone for HEntry<K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            HEntry::Leaf(hash, k, v) => HEntry::Leaf(*hash, k.clone(), v.clone()),
+            HEntry::Node(node) => HEntry::Node(Arc::clone(node)),
+        }
+    }
+}
+
+/// The position of a hash in a `Branch` at `shift`.
+fn bit_of(hash: u64, shift: usize) -> u32 {
+    1 << ((hash >> shift) as usize & MASK)
+}
+
+impl<K, V> HNode<K, V> {
+    fn empty() -> Self {
+        HNode::Branch {
+            bitmap: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    fn entries(&self) -> &[HEntry<K, V>] {
+        match self {
+            HNode::Branch { entries, .. } => entries,
+            HNode::Collision(entries) => entries,
+        }
+    }
+
+    /// A node that holds two leaves with different keys.
+    fn pair(shift: usize, leaf1: HEntry<K, V>, leaf2: HEntry<K, V>) -> Self {
+        let (hash1, hash2) = match (&leaf1, &leaf2) {
+            (HEntry::Leaf(hash1, _, _), HEntry::Leaf(hash2, _, _)) => (*hash1, *hash2),
+            _ => unreachable!(),
+        };
+        if shift >= HASH_BITS {
+            return HNode::Collision(vec![leaf1, leaf2]);
+        }
+        let (bit1, bit2) = (bit_of(hash1, shift), bit_of(hash2, shift));
+        if bit1 == bit2 {
+            let node = HNode::pair(shift + BITS, leaf1, leaf2);
+            HNode::Branch {
+                bitmap: bit1,
+                entries: vec![HEntry::Node(Arc::new(node))],
+            }
+        } else {
+            let entries = if bit1 < bit2 {
+                vec![leaf1, leaf2]
+            } else {
+                vec![leaf2, leaf1]
+            };
+            HNode::Branch {
+                bitmap: bit1 | bit2,
+                entries,
+            }
+        }
+    }
+
+    fn get<Q>(&self, hash: u64, shift: usize, key: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match self {
+            HNode::Branch { bitmap, entries } => {
+                let bit = bit_of(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                match &entries[(bitmap & (bit - 1)).count_ones() as usize] {
+                    HEntry::Leaf(h, k, v) if *h == hash && k.borrow() == key => Some(v),
+                    HEntry::Leaf(..) => None,
+                    HEntry::Node(node) => node.get(hash, shift + BITS, key),
+                }
+            }
+            HNode::Collision(entries) => entries.iter().find_map(|entry| match entry {
+                HEntry::Leaf(h, k, v) if *h == hash && k.borrow() == key => Some(v),
+                _ => None,
+            }),
+        }
+    }
+
+    /// The single leaf of this node, if it only has a single leaf.
+    fn single_leaf(&mut self) -> Option<HEntry<K, V>> {
+        let entries = match self {
+            HNode::Branch { entries, .. } => entries,
+            HNode::Collision(entries) => entries,
+        };
+        match entries.as_slice() {
+            [HEntry::Leaf(..)] => entries.pop(),
+            _ => None,
+        }
+    }
+}
+
+impl<K: Clone + Eq, V: Clone> HNode<K, V> {
+    fn get_mut<Q>(&mut self, hash: u64, shift: usize, key: &Q) -> Option<&mut V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match self {
+            HNode::Branch { bitmap, entries } => {
+                let bit = bit_of(hash, shift);
+                if *bitmap & bit == 0 {
+                    return None;
+                }
+                match &mut entries[(*bitmap & (bit - 1)).count_ones() as usize] {
+                    HEntry::Leaf(h, k, v) if *h == hash && (*k).borrow() == key => Some(v),
+                    HEntry::Leaf(..) => None,
+                    HEntry::Node(node) => Arc::make_mut(node).get_mut(hash, shift + BITS, key),
+                }
+            }
+            HNode::Collision(entries) => entries.iter_mut().find_map(|entry| match entry {
+                HEntry::Leaf(h, k, v) if *h == hash && (*k).borrow() == key => Some(v),
+                _ => None,
+            }),
+        }
+    }
+
+    fn insert(&mut self, hash: u64, shift: usize, key: K, value: V) -> Option<V> {
+        match self {
+            HNode::Branch { bitmap, entries } => {
+                let bit = bit_of(hash, shift);
+                let position = (*bitmap & (bit - 1)).count_ones() as usize;
+                if *bitmap & bit == 0 {
+                    *bitmap |= bit;
+                    entries.insert(position, HEntry::Leaf(hash, key, value));
+                    return None;
+                }
+                match &mut entries[position] {
+                    HEntry::Leaf(h, k, v) if *h == hash && *k == key => {
+                        Some(std::mem::replace(v, value))
+                    }
+                    HEntry::Leaf(..) => {
+                        let leaf = std::mem::replace(
+                            &mut entries[position],
+                            HEntry::Node(Arc::new(HNode::empty())),
+                        );
+                        let node = HNode::pair(shift + BITS, leaf, HEntry::Leaf(hash, key, value));
+                        entries[position] = HEntry::Node(Arc::new(node));
+                        None
+                    }
+                    HEntry::Node(node) => {
+                        Arc::make_mut(node).insert(hash, shift + BITS, key, value)
+                    }
+                }
+            }
+            HNode::Collision(entries) => {
+                for entry in entries.iter_mut() {
+                    if let HEntry::Leaf(_, k, v) = entry {
+                        if *k == key {
+                            return Some(std::mem::replace(v, value));
+                        }
+                    }
+                }
+                entries.push(HEntry::Leaf(hash, key, value));
+                None
+            }
+        }
+    }
+
+    fn remove<Q>(&mut self, hash: u64, shift: usize, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match self {
+            HNode::Branch { bitmap, entries } => {
+                let bit = bit_of(hash, shift);
+                if *bitmap & bit == 0 {
+                    return None;
+                }
+                let position = (*bitmap & (bit - 1)).count_ones() as usize;
+                match &mut entries[position] {
+                    HEntry::Leaf(h, k, _) if *h == hash && (*k).borrow() == key => {
+                        *bitmap &= !bit;
+                        match entries.remove(position) {
+                            HEntry::Leaf(_, _, v) => Some(v),
+                            HEntry::Node(_) => unreachable!(),
+                        }
+                    }
+                    HEntry::Leaf(..) => None,
+                    HEntry::Node(node) => {
+                        let node = Arc::make_mut(node);
+                        let value = node.remove(hash, shift + BITS, key)?;
+                        // pulls up a single leaf, to keep the trie compact
+                        if let Some(leaf) = node.single_leaf() {
+                            entries[position] = leaf;
+                        }
+                        Some(value)
+                    }
+                }
+            }
+            HNode::Collision(entries) => {
+                let position = entries.iter().position(|entry| match entry {
+                    HEntry::Leaf(_, k, _) => k.borrow() == key,
+                    HEntry::Node(_) => false,
+                })?;
+                match entries.remove(position) {
+                    HEntry::Leaf(_, _, v) => Some(v),
+                    HEntry::Node(_) => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> PMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, V, S> PMap<K, V, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            root: Arc::new(HNode::empty()),
+            len: 0,
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether both maps share the same root, ie. whether one is an
+    /// unmodified clone of the other.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.root, &other.root)
+    }
+
+    pub fn iter(&self) -> PMapIter<'_, K, V> {
+        PMapIter {
+            stack: vec![self.root.entries().iter()],
+            remaining: self.len,
+        }
+    }
+}
+
+impl<K, V, S> PMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        self.hasher.hash_one(key)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.root.get(self.hash(key), 0, key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+}
+
+impl<K, V, S> PMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        Arc::make_mut(&mut self.root).get_mut(hash, 0, key)
+    }
+
+    /// Inserts a value, returning the old value of the `key`, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hash(&key);
+        let old = Arc::make_mut(&mut self.root).insert(hash, 0, key, value);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        let old = Arc::make_mut(&mut self.root).remove(hash, 0, key)?;
+        self.len -= 1;
+        Some(old)
+    }
+}
+
+impl<K, V, S: Clone> Clone for PMap<K, V, S> {
+    /// Only clones the root `Arc`.
+    fn clone(&self) -> Self {
+        Self {
+            root: Arc::clone(&self.root),
+            len: self.len,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<K, V, S: Default> Default for PMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for PMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for PMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && (self.ptr_eq(other) || self.iter().all(|(k, v)| other.get(k) == Some(v)))
+    }
+}
+
+impl<K, V, S> Eq for PMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> Extend<(K, V)> for PMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            let _old = self.insert(k, v);
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for PMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a PMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = PMapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct PMapIter<'a, K, V> {
+    stack: Vec<std::slice::Iter<'a, HEntry<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for PMapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(HEntry::Leaf(_, k, v)) => {
+                    self.remaining -= 1;
+                    return Some((k, v));
+                }
+                Some(HEntry::Node(node)) => self.stack.push(node.entries().iter()),
+                None => {
+                    let _finished = self.stack.pop();
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
@@ -1,18 +1,86 @@
-use super::{target, Apply, Chain, ConsumedToken, PartialApply, Take, TakeOwned, Token};
+use super::{
+    target, Apply, ApplyReversible, ApplyScratch, Chain, ConsumedToken, PartialApply,
+    PartialPending, PartialScratch, PartialSwap, Scratch, Take, TakeOwned, Token, Undo,
+};
 use std::marker::PhantomData;
 
+/// Information to diverge how the copy of `T` is made and modified.
+pub mod mode {
+    /// The copy is a `Clone` of `T`, and the closure receives `&mut T`.
+    pub struct Copied;
+
+    /// `T` is an `Arc<U>`, the copy is a clone of the `Arc`, and the
+    /// closure receives a `&mut cow::ArcMut<U>`, which only clones `U`
+    /// on the first mut access.
+    pub struct MakeMut;
+
+    /// There is no copy until the first mut access, and the closure
+    /// receives a `&mut cow::CowMut<T>`, which borrows the original `T`
+    /// until then.
+    pub struct Lazy;
+
+    /// There is no copy until the first mut access into a field, and the
+    /// closure receives the overlay of `T`, which only clones the fields
+    /// that get mutably accessed (see `overlay!`).
+    pub struct Overlay;
+
+    /// The copy is a `Clone` of `T`, and after the modification it's
+    /// compared with `T` by `PartialEq`, so that an equal copy doesn't
+    /// replace `T`.
+    pub struct Compared;
+
+    /// The copy is a `Clone` of `T`, and after the modification it's
+    /// compared with `T` by `change::ContentHash`, and on equal hashes
+    /// also by `PartialEq`, so that an equal copy doesn't replace `T`.
+    pub struct Hashed;
+
+    /// The copy is a `Clone` of `T`, and after the modification it's
+    /// compared with `T` by `PartialEq`, so that an equal copy turns the
+    /// appliance into an error (see `change::Unchanged`).
+    pub struct MustChange;
+
+    /// Same as `MustChange`, but the comparison is made by a predicate,
+    /// which is stored alongside the closure.
+    pub struct MustChangeBy;
+
+    /// The copy is a `Clone` of `T`, and after the modification it must
+    /// pass `invariant::Invariant::check()` to be replaced into `T`.
+    pub struct Checked;
+
+    /// `OuterT` is a tuple of `OneMut`s, the copy is a tuple of `Clone`s,
+    /// and a single closure receives a tuple of mut accesses into all of
+    /// them (see `joint::prepare_joint()`).
+    pub struct Joint;
+
+    /// `OuterT` is a set of `stm::TVar`s, the copy is a tuple of their
+    /// values, and the closure is re-run until it's run is validated
+    /// against all of the `TVar`s it observed (see `stm::atomically()`).
+    pub struct Atomically;
+
+    /// `OuterT` is a `history::Edit`, the copy is a `Clone` of `T`, and
+    /// the replaced `T` is recorded as an undo state of the `History`
+    /// (see `History::edit()`).
+    pub struct Recorded;
+}
+
 /// Holds a single scoped modification into a copy of `T`.
 /// The copy receives the modification lazily, and at the late stage
 /// of `Prepared::apply`, the original value `T` is replaced by the
 /// modified copy.
-pub struct Prepared<OuterT, T, F, E> {
-    inner: OuterT,
-    f: F,
+///
+/// `M` indicates how the copy is made, see `mode`.
+pub struct Prepared<OuterT, T, F, E, M = mode::Copied> {
+    pub(crate) inner: OuterT,
+    pub(crate) f: F,
     _t: PhantomData<T>,
     _err: PhantomData<E>,
+    _mode: PhantomData<M>,
 }
 
-impl<'t, OuterT, T, FInner, E> Take<FInner, target::Function> for Prepared<OuterT, T, FInner, E> {
+#[allow(clippy::extra_unused_lifetimes)]
+impl<'t, OuterT, T, FInner, E, M> Take<FInner, target::Function>
+    for Prepared<OuterT, T, FInner, E, M>
+{
     fn take_ref(&self) -> &FInner {
         &self.f
     }
@@ -22,8 +90,8 @@ impl<'t, OuterT, T, FInner, E> Take<FInner, target::Function> for Prepared<Outer
     }
 }
 
-impl<'t, OuterT, T, FInner, E> TakeOwned<Token<'t, T>, target::Token>
-    for Prepared<OuterT, T, FInner, E>
+impl<'t, OuterT, T, FInner, E, M> TakeOwned<Token<'t, T>, target::Token>
+    for Prepared<OuterT, T, FInner, E, M>
 where
     OuterT: TakeOwned<Token<'t, T>, target::Token>,
 {
@@ -37,11 +105,19 @@ where
 
 impl<OuterT, T, F, E> Prepared<OuterT, T, F, E> {
     pub fn new(outer: OuterT, f: F) -> Self {
+        Self::with_mode(outer, f)
+    }
+}
+
+impl<OuterT, T, F, E, M> Prepared<OuterT, T, F, E, M> {
+    /// Creates a `Prepared` modification with a different `mode`.
+    pub fn with_mode(outer: OuterT, f: F) -> Self {
         Self {
             inner: outer,
             f,
             _t: PhantomData,
             _err: PhantomData,
+            _mode: PhantomData,
         }
     }
 
@@ -74,19 +150,21 @@ where {
     }
 }
 
-impl<'t, OuterT, T, F, O, E> PartialApply<T, F, O, E> for Prepared<OuterT, T, F, E>
+impl<'t, OuterT, T, F, O, E> PartialApply<T, F, O, E> for Prepared<OuterT, T, F, E, mode::Copied>
 where
     OuterT: Take<T, target::Type> + Take<Token<'t, T>, target::Token>,
     F: FnOnce(&mut T) -> Result<O, E>,
     T: 't + Clone,
     OuterT: 't,
 {
+    type Next = T;
+
     fn get_next(&self) -> T {
         let next: &T = self.inner.take_ref();
         next.clone()
     }
 
-    fn modify_next(mut next: T, f: F) -> Result<(O, T), E> {
+    fn modify_next(&self, mut next: T, f: F) -> Result<(O, T), E> {
         let o = (f)(&mut next)?;
         Ok((o, next))
     }
@@ -97,10 +175,22 @@ where
     }
 }
 
-unsafe impl<'t, OuterT, T, F, O, E> Apply<'t, T, F, O, E> for Prepared<OuterT, T, F, E>
+impl<'a, OuterT, T, F, O, E> PartialPending<'a, T, F, O, E>
+    for Prepared<OuterT, T, F, E, mode::Copied>
+where
+    Self: PartialApply<T, F, O, E, Next = T>,
+{
+    type View = &'a T;
+
+    fn pending(&'a self, next: &'a T) -> &'a T {
+        next
+    }
+}
+
+unsafe impl<'t, OuterT, T, F, O, E, M> Apply<'t, T, F, O, E> for Prepared<OuterT, T, F, E, M>
 where
     Self: PartialApply<T, F, O, E>,
-    OuterT: Take<Token<'t, T>, target::Token> + TakeOwned<Token<'t, T>, target::Token>,
+    OuterT: TakeOwned<Token<'t, T>, target::Token>,
     T: 't,
     E: 't,
     F: 't + Clone,
@@ -110,7 +200,7 @@ where
         let next = self.get_next();
         let f = self.f.clone();
 
-        let (o, next) = match Self::modify_next(next, f) {
+        let (o, next) = match self.modify_next(next, f) {
             Ok(v) => v,
             Err(e) => {
                 // Safety:
@@ -136,3 +226,111 @@ where
         Ok((o, consumed))
     }
 }
+
+impl<OuterT, T, F, E, M> PartialSwap<T> for Prepared<OuterT, T, F, E, M>
+where
+    OuterT: Take<T, target::Type>,
+{
+    fn swap(&mut self, next: T) -> T {
+        let current: &mut T = self.inner.take_mut();
+        std::mem::replace(current, next)
+    }
+}
+
+unsafe impl<'t, OuterT, T, F, O, E, M> ApplyReversible<'t, T, F, O, E>
+    for Prepared<OuterT, T, F, E, M>
+where
+    Self: PartialApply<T, F, O, E, Next = T> + PartialSwap<T>,
+    OuterT: TakeOwned<Token<'t, T>, target::Token>,
+    T: 't,
+    F: Clone,
+{
+    fn commit_reversible(mut self) -> crate::AllOrNone<'t, (O, Undo<Self, T>), E, T> {
+        let next = self.get_next();
+        let f = self.f.clone();
+
+        let (o, next) = match self.modify_next(next, f) {
+            Ok(v) => v,
+            Err(e) => {
+                // Safety:
+                //
+                // this is indicating that the mutation failed,
+                // and also preventing further mutations
+                let t = unsafe { self.inner.take_owned() };
+                return Err((e, t));
+            }
+        };
+        // Safety:
+        //
+        // only swap after the modifications were successful.
+        // Also, after this, an `Ok` return is guaranteed
+        let old = self.swap(next);
+
+        // the original `Token` stays inside of the `Undo`, in case
+        // the old value gets rolled back
+        let consumed = ConsumedToken::from(Token(PhantomData));
+        Ok(((o, Undo::new(self, old)), consumed))
+    }
+}
+
+impl<OuterT, T, F, O, E> PartialScratch<T, F, O, E> for Prepared<OuterT, T, F, E, mode::Copied>
+where
+    OuterT: Take<T, target::Type>,
+    F: FnOnce(&mut T) -> Result<O, E>,
+    T: Clone,
+{
+    fn clone_into_next(&self, next: &mut T) {
+        let current: &T = self.inner.take_ref();
+        next.clone_from(current);
+    }
+
+    fn modify_in_place(&self, next: &mut T, f: F) -> Result<O, E> {
+        (f)(next)
+    }
+}
+
+unsafe impl<'t, OuterT, T, F, O, E, M> ApplyScratch<'t, T, F, O, E> for Prepared<OuterT, T, F, E, M>
+where
+    Self: PartialApply<T, F, O, E, Next = T> + PartialScratch<T, F, O, E> + PartialSwap<T>,
+    OuterT: TakeOwned<Token<'t, T>, target::Token>,
+    T: 't,
+    F: Clone,
+{
+    fn apply_with(mut self, scratch: &mut Scratch<T>) -> crate::AllOrNone<'t, O, E, T> {
+        let mut next = match scratch.take() {
+            Some(mut next) => {
+                self.clone_into_next(&mut next);
+                next
+            }
+            None => self.get_next(),
+        };
+        let f = self.f.clone();
+
+        let o = match self.modify_in_place(&mut next, f) {
+            Ok(o) => o,
+            Err(e) => {
+                scratch.put(next);
+                // Safety:
+                //
+                // this is indicating that the mutation failed,
+                // and also preventing further mutations
+                let t = unsafe { self.inner.take_owned() };
+                return Err((e, t));
+            }
+        };
+        // Safety:
+        //
+        // only swap after the modifications were successful.
+        // Also, after this, an `Ok` return is guaranteed
+        let old = self.swap(next);
+        scratch.put(old);
+
+        // Safety:
+        //
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        let t = unsafe { self.inner.take_owned() };
+        let consumed = ConsumedToken::from(t);
+        Ok((o, consumed))
+    }
+}
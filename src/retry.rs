@@ -0,0 +1,317 @@
+use super::{
+    and_then, target, AndThen, Apply, Chain, ConsumedToken, OneMut, PartialApply, PartialPending,
+    Prepared, Take, TakeOwned, Token,
+};
+use std::time::Duration;
+
+/// What to do after a failed attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Retry {
+    /// Tries again right away.
+    Now,
+    /// Tries again after sleeping for the duration.
+    After(Duration),
+    /// Stops retrying, and the appliance fails with the last error.
+    GiveUp,
+}
+
+/// Decides, for each failed attempt, whether the modification should be
+/// tried again.
+///
+/// `attempt` starts at `1`, for the first failure.
+///
+/// Closures of the form `FnMut(usize, &E) -> Retry` are also policies.
+pub trait RetryPolicy<E> {
+    fn on_error(&mut self, attempt: usize, e: &E) -> Retry;
+}
+
+impl<E, P> RetryPolicy<E> for P
+where
+    P: FnMut(usize, &E) -> Retry,
+{
+    fn on_error(&mut self, attempt: usize, e: &E) -> Retry {
+        (self)(attempt, e)
+    }
+}
+
+/// Classifies which errors are transient, and so can be retried.
+///
+/// Closures of the form `Fn(&E) -> bool` are also classifiers.
+pub trait Classify<E> {
+    fn is_transient(&self, e: &E) -> bool;
+}
+
+impl<E, C> Classify<E> for C
+where
+    C: Fn(&E) -> bool,
+{
+    fn is_transient(&self, e: &E) -> bool {
+        (self)(e)
+    }
+}
+
+/// Classifies every error as transient.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllErrors;
+
+impl<E> Classify<E> for AllErrors {
+    fn is_transient(&self, _e: &E) -> bool {
+        true
+    }
+}
+
+/// A policy that retries up to a maximum amount of times, with a delay
+/// that gets multiplied by `factor` after each retry.
+///
+/// Only errors that `C` classifies as transient are retried; by default
+/// all of them are.
+#[derive(Clone, Debug)]
+pub struct Backoff<C = AllErrors> {
+    max_retries: usize,
+    delay: Duration,
+    factor: u32,
+    max_delay: Option<Duration>,
+    classify: C,
+}
+
+impl Backoff {
+    /// Retries immediately, up to `max_retries` times.
+    pub fn new(max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            delay: Duration::ZERO,
+            factor: 1,
+            max_delay: None,
+            classify: AllErrors,
+        }
+    }
+}
+
+impl<C> Backoff<C> {
+    /// The delay before the first retry.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Multiplies the delay by `factor` after each retry.
+    pub fn exponential(mut self, factor: u32) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// Caps the delay between retries.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Only retries errors that are classified as transient.
+    pub fn when<C2>(self, classify: C2) -> Backoff<C2> {
+        Backoff {
+            max_retries: self.max_retries,
+            delay: self.delay,
+            factor: self.factor,
+            max_delay: self.max_delay,
+            classify,
+        }
+    }
+}
+
+impl<E, C> RetryPolicy<E> for Backoff<C>
+where
+    C: Classify<E>,
+{
+    fn on_error(&mut self, attempt: usize, e: &E) -> Retry {
+        if attempt > self.max_retries {
+            return Retry::GiveUp;
+        }
+        if !self.classify.is_transient(e) {
+            return Retry::GiveUp;
+        }
+        let delay = self.delay;
+        self.delay = delay.saturating_mul(self.factor);
+        if let Some(max_delay) = self.max_delay {
+            self.delay = self.delay.min(max_delay);
+        }
+        if delay.is_zero() {
+            Retry::Now
+        } else {
+            Retry::After(delay)
+        }
+    }
+}
+
+/// A modification that gets retried, on a fresh copy, according to a
+/// `RetryPolicy`.
+///
+/// The original `T` and the `Token` are only resolved once an attempt
+/// succeeds or the policy gives up.
+///
+/// See `Prepared::retry()`.
+pub struct Retrying<A, F, P> {
+    a: A,
+    fs: (F, P),
+}
+
+impl<OuterT, T, F, E, M> Prepared<OuterT, T, F, E, M> {
+    /// Retries this modification according to the `policy`.
+    ///
+    /// Each attempt starts from a fresh copy of `T`, so the partial
+    /// modifications of failed attempts are discarded.
+    pub fn retry<P>(self, policy: P) -> Retrying<Self, F, P>
+    where
+        F: Clone,
+    {
+        let f = self.f.clone();
+        Retrying {
+            a: self,
+            fs: (f, policy),
+        }
+    }
+}
+
+impl<A, F, P> Retrying<A, F, P> {
+    /// Chains this modification with another one, so that both are
+    /// committed in the same transaction.
+    pub fn chain<A2>(self, a2: A2) -> Chain<Self, A2> {
+        Chain::new(self, a2)
+    }
+
+    /// Adds a modification into `T2`, whose closure receives the output
+    /// of this one, see `Prepared::and_then()`.
+    pub fn and_then<'t2, T2, G2>(
+        self,
+        b: OneMut<'t2, T2>,
+        g2: G2,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, (F, P), G2>
+    where
+        F: Clone,
+        P: Clone,
+    {
+        AndThen::new(self, b, g2)
+    }
+
+    /// Adds a modification into `T2`, whose closure can read the modified
+    /// copy of this one, see `Prepared::and_then_pending()`.
+    #[allow(clippy::type_complexity)]
+    pub fn and_then_pending<'t2, T2, G2>(
+        self,
+        b: OneMut<'t2, T2>,
+        g2: G2,
+    ) -> AndThen<Self, OneMut<'t2, T2>, T2, (F, P), G2, and_then::link::Pending>
+    where
+        F: Clone,
+        P: Clone,
+    {
+        AndThen::new(self, b, g2)
+    }
+}
+
+impl<A, F, P> Take<(F, P), target::Function> for Retrying<A, F, P> {
+    fn take_ref(&self) -> &(F, P) {
+        &self.fs
+    }
+
+    fn take_mut(&mut self) -> &mut (F, P) {
+        &mut self.fs
+    }
+}
+
+impl<'t, A, F, P, T> TakeOwned<Token<'t, T>, target::Token> for Retrying<A, F, P>
+where
+    A: TakeOwned<Token<'t, T>, target::Token>,
+{
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'t, T> {
+        self.a.take_owned()
+    }
+}
+
+impl<A, T, F, P, O, E> PartialApply<T, (F, P), O, E> for Retrying<A, F, P>
+where
+    A: PartialApply<T, F, O, E>,
+    F: Clone,
+    P: RetryPolicy<E>,
+{
+    type Next = A::Next;
+
+    fn get_next(&self) -> A::Next {
+        self.a.get_next()
+    }
+
+    fn modify_next(&self, next: A::Next, (f, mut policy): (F, P)) -> Result<(O, A::Next), E> {
+        let mut next = next;
+        let mut attempt = 0;
+        loop {
+            let e = match self.a.modify_next(next, f.clone()) {
+                Ok(v) => return Ok(v),
+                Err(e) => e,
+            };
+            attempt += 1;
+            match policy.on_error(attempt, &e) {
+                Retry::Now => {}
+                Retry::After(delay) => std::thread::sleep(delay),
+                Retry::GiveUp => return Err(e),
+            }
+            // the partially modified copy was discarded
+            next = self.a.get_next();
+        }
+    }
+
+    fn replace(&mut self, next: A::Next) {
+        self.a.replace(next)
+    }
+}
+
+impl<'a, A, T, F, P, O, E> PartialPending<'a, T, (F, P), O, E> for Retrying<A, F, P>
+where
+    Self: PartialApply<T, (F, P), O, E, Next = A::Next>,
+    A: PartialPending<'a, T, F, O, E>,
+{
+    type View = A::View;
+
+    fn pending(&'a self, next: &'a A::Next) -> A::View {
+        self.a.pending(next)
+    }
+}
+
+unsafe impl<'t, A, T, F, P, O, E> Apply<'t, T, (F, P), O, E> for Retrying<A, F, P>
+where
+    Self: PartialApply<T, (F, P), O, E>,
+    A: TakeOwned<Token<'t, T>, target::Token>,
+    T: 't,
+    F: Clone,
+    P: Clone,
+{
+    fn apply(mut self) -> crate::AllOrNone<'t, O, E, T> {
+        let next = self.get_next();
+        let fs = self.fs.clone();
+
+        let (o, next) = match self.modify_next(next, fs) {
+            Ok(v) => v,
+            Err(e) => {
+                // Safety:
+                //
+                // this is indicating that the policy gave up,
+                // and also preventing further mutations
+                let t = unsafe { self.take_owned() };
+                return Err((e, t));
+            }
+        };
+        // Safety:
+        //
+        // only replace after the modifications were successful.
+        // Also, after this, an `Ok` return is guaranteed
+        self.replace(next);
+
+        // Safety:
+        //
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        let t = unsafe { self.take_owned() };
+        Ok((o, ConsumedToken::from(t)))
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{AllOrNone, Apply, OneMut};
+use std::ops::{Deref, DerefMut};
+
+/// Runs an optional sub-step over the working copy `T` of a `prepare()`
+/// closure, as a nested transaction.
+///
+/// `f` modifies a copy of the working copy, which is only written back on
+/// `Ok`. On `Err`, the working copy is kept intact, so the outer closure
+/// may continue. Either way, the outer transaction still decides whether
+/// anything gets committed into the original `T`.
+pub fn nested<'t, T, F, O, E>(copy: &'t mut T, f: F) -> AllOrNone<'t, O, E, T>
+where
+    T: Clone,
+    F: FnOnce(&mut T) -> Result<O, E> + Clone + 't,
+    E: 't,
+{
+    OneMut::new(copy).unchecked_prepare(f).apply()
+}
+
+/// A savepoint into the working copy `T` of a `prepare()` closure.
+///
+/// Modifications made through the savepoint go directly into the working
+/// copy, and the state from when the savepoint was created can be restored
+/// by `rollback()`. Dropping the savepoint without `release()` also rolls
+/// it back.
+pub struct Savepoint<'a, T> {
+    copy: &'a mut T,
+    saved: Option<T>,
+}
+
+impl<'a, T: Clone> Savepoint<'a, T> {
+    pub fn new(copy: &'a mut T) -> Self {
+        let saved = Some(copy.clone());
+        Self { copy, saved }
+    }
+}
+
+impl<'a, T> Savepoint<'a, T> {
+    /// Keeps the modifications made since the savepoint.
+    pub fn release(mut self) {
+        self.saved = None;
+    }
+
+    /// Restores the state from when the savepoint was created.
+    pub fn rollback(self) {}
+}
+
+impl<'a, T> Deref for Savepoint<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.copy
+    }
+}
+
+impl<'a, T> DerefMut for Savepoint<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.copy
+    }
+}
+
+impl<'a, T> Drop for Savepoint<'a, T> {
+    fn drop(&mut self) {
+        if let Some(saved) = self.saved.take() {
+            *self.copy = saved;
+        }
+    }
+}
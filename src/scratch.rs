@@ -0,0 +1,99 @@
+use crate::AllOrNone;
+
+/// A reusable buffer for the copies of `T`.
+///
+/// An `ApplyScratch::apply_with()` fills the buffer with a copy of the
+/// original `T` by `Clone::clone_from`, which may reuse the buffer's
+/// allocations, and then modifies the copy in place.
+///
+/// - On success, the copy is swapped with the original `T`, so the
+///   buffer receives the old original `T`, to be reused by the next
+///   appliance;
+/// - On failure, the buffer keeps the discarded copy.
+///
+/// This means that repeated appliances of the same shape reach a
+/// steady state where no allocation is made, at least for types such
+/// as `Vec` and `String` that implement `clone_from` by reusing
+/// their allocations.
+///
+/// For a `Chain`, a `Scratch` of the tuple of it's values keeps the
+/// buffers of every member. This is also why the copies are not placed
+/// into a bump arena: the allocations that matter are made by `T`'s own
+/// `Clone`, which an arena can't serve, and `clone_from` already
+/// reuses them.
+pub struct Scratch<T> {
+    slot: Option<T>,
+}
+
+impl<T> Scratch<T> {
+    /// An empty buffer, so the first appliance will `Clone` the original `T`.
+    pub fn new() -> Self {
+        Self { slot: None }
+    }
+
+    /// A buffer that already holds a value, whose resources may be reused.
+    pub fn with(value: T) -> Self {
+        Self { slot: Some(value) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slot.is_none()
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.slot
+    }
+
+    pub(crate) fn take(&mut self) -> Option<T> {
+        self.slot.take()
+    }
+
+    pub(crate) fn put(&mut self, value: T) {
+        self.slot = Some(value);
+    }
+}
+
+impl<T> Default for Scratch<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Modifies a copy of `T` in place, so that the copy's resources can be
+/// kept regardless of the modification's result.
+///
+/// Trait types:
+///
+/// - `T` is the protected type.
+/// - `F` is the scoped closure that will mut access `T`.
+/// - `O` is `F`'s `Ok` return type.
+/// - `E` is `F`'s `Err` return type.
+pub trait PartialScratch<T, F, O, E> {
+    /// Overwrites `next` with a copy of the original `T`, reusing
+    /// `next`'s resources.
+    fn clone_into_next(&self, next: &mut T);
+    /// Applies a modification into `next` (presumably the copy of `T`).
+    fn modify_in_place(&self, next: &mut T, f: F) -> Result<O, E>;
+}
+
+/// # Safety
+///
+/// Has the same requirements as `Apply`.
+///
+///
+/// Trait types:
+///
+/// - `T` is the protected type.
+/// - `F` is the scoped closure that will mut access `T`.
+/// - `O` is `F`'s `Ok` return type.
+/// - `E` is `F`'s `Err` return type.
+pub unsafe trait ApplyScratch<'t, T, F, O, E> {
+    /// Copies `T` into the `scratch`, modifies it, and then swaps it
+    /// with the original `T`.
+    ///
+    /// - `Ok` implies the original `T` got completely modified,
+    ///   and the `scratch` holds it's old value,
+    /// - `Err` implies the original `T` is untouched, and the `scratch`
+    ///   holds the discarded copy.
+    fn apply_with(self, scratch: &mut Scratch<T>) -> AllOrNone<'t, O, E, T>;
+}
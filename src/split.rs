@@ -1,12 +1,23 @@
 use crate::{OneMut, Token};
 use paste::paste;
 use std::marker::PhantomData;
+use std::ops::DerefMut;
 
 pub trait Split<Lifetimes> {
     type Return;
     fn split(self) -> Self::Return;
 }
 
+/// Creates `OneMut`s from a tuple of guards, such as the ones
+/// from `lock::LockAll` or `cell::BorrowAllMut`.
+///
+/// The `OneMut`s borrow from the guards, so the guards are only
+/// released after the `OneMut`s (and their appliances) are finished.
+pub trait OneMuts<'a> {
+    type Return;
+    fn one_muts(self) -> Self::Return;
+}
+
 // 12 11 10 9 8 7 6 5 4 3 2 1
 
 #[allow(unused_macros)]
@@ -72,8 +83,28 @@ macro_rules! split_impls {
                 >;
             }
 
-
-
+            impl<
+                'a,
+                [<G $last>],
+            > OneMuts<'a> for &'a mut (
+                [<G $last>],
+            )
+            where
+                [<G $last>]: DerefMut,
+                [<G $last>]::Target: Sized,
+            {
+                type Return = (
+                    OneMut<'a, [<G $last>]::Target>,
+                );
+                fn one_muts(self) -> Self::Return {
+                    let (
+                        [<g $last>],
+                    ) = self;
+                    (
+                        OneMut::new(&mut **[<g $last>]),
+                    )
+                }
+            }
         }
     };
     ( $first:tt, $( $tail:tt),+  ) => {
@@ -152,6 +183,36 @@ macro_rules! split_impls {
                 >;
             }
 
+            impl<
+                'a,
+                [<G $first>],
+                $( [<G $tail>], )+
+            > OneMuts<'a> for &'a mut (
+                [<G $first>],
+                $( [<G $tail>], )+
+            )
+            where
+                [<G $first>]: DerefMut,
+                $( [<G $tail>]: DerefMut, )+
+                [<G $first>]::Target: Sized,
+                $( [<G $tail>]::Target: Sized, )+
+            {
+                type Return = (
+                    OneMut<'a, [<G $first>]::Target>,
+                    $( OneMut<'a, [<G $tail>]::Target>, )+
+                );
+                fn one_muts(self) -> Self::Return {
+                    let (
+                        [<g $first>],
+                        $( [<g $tail>], )+
+                    ) = self;
+                    (
+                        OneMut::new(&mut **[<g $first>]),
+                        $( OneMut::new(&mut **[<g $tail>]), )+
+                    )
+                }
+            }
+
             split_impls! { $( $tail ),+  }
         }
     };
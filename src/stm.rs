@@ -0,0 +1,571 @@
+use crate::prepared::mode;
+use crate::versioned::{Conflict, Pause, VersionedGuard};
+use crate::{
+    target, AllOrNone, Apply, PartialApply, PartialPending, Prepared, TakeOwned, Token, Versioned,
+};
+use paste::paste;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+thread_local! {
+    /// The addresses of the `TVar`s that this thread holds, from a
+    /// validated run of an `atomically()` closure until it's commit.
+    static HELD: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Panics if this thread holds the `TVar` at `address`, as accessing it
+/// would otherwise block forever.
+fn assert_not_held(address: usize) {
+    let held = HELD.with(|held| held.borrow().contains(&address));
+    assert!(
+        !held,
+        "the TVar is held by an earlier member of the same transaction"
+    );
+}
+
+/// Registers `TVar`s as held by this thread, until it's dropped.
+struct Holding(Vec<usize>);
+
+impl Holding {
+    fn new(addresses: Vec<usize>) -> Self {
+        HELD.with(|held| held.borrow_mut().extend_from_slice(&addresses));
+        Self(addresses)
+    }
+}
+
+impl Drop for Holding {
+    fn drop(&mut self) {
+        HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            for address in self.0.iter() {
+                if let Some(i) = held.iter().position(|h| h == address) {
+                    held.swap_remove(i);
+                }
+            }
+        });
+    }
+}
+
+/// A transactional variable.
+///
+/// `TVar`s are read and written by `atomically()` transactions,
+/// and each commit into a `TVar` increases it's version.
+pub struct TVar<T> {
+    inner: Versioned<T>,
+}
+
+impl<T> TVar<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Versioned::new(value),
+        }
+    }
+
+    /// How many commits happened into `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this thread holds the `TVar` for a commit, see
+    /// `prepare_atomically()`.
+    pub fn version(&self) -> u64 {
+        assert_not_held(self.address());
+        self.inner.version()
+    }
+
+    /// Creates a copy of the current `T`, outside of any transaction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this thread holds the `TVar` for a commit, see
+    /// `prepare_atomically()`.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        assert_not_held(self.address());
+        self.inner.get()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+/// Type-erased access into the `TVar`s that were read by a `Tx`.
+trait Read {
+    fn address(&self) -> usize;
+    fn version(&self) -> u64;
+    fn try_lock_version<'g>(&'g self, version: u64) -> Result<Box<dyn Held + 'g>, Conflict>;
+}
+
+/// A held guard.
+trait Held {}
+impl<T> Held for T {}
+
+impl<T> Read for TVar<T> {
+    fn address(&self) -> usize {
+        TVar::address(self)
+    }
+
+    fn version(&self) -> u64 {
+        TVar::version(self)
+    }
+
+    fn try_lock_version<'g>(&'g self, version: u64) -> Result<Box<dyn Held + 'g>, Conflict> {
+        let guard = self.inner.try_lock_version(version)?;
+        Ok(Box::new(guard))
+    }
+}
+
+/// Records the `TVar`s that got read during a single run of an
+/// `atomically()` closure.
+pub struct Tx<'a> {
+    reads: Vec<(&'a dyn Read, u64)>,
+}
+
+impl<'a> Tx<'a> {
+    /// Creates a copy of the current `T`, and records it's version,
+    /// which is validated at commit time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this thread holds the `TVar` for a commit, see
+    /// `prepare_atomically()`.
+    pub fn read<T: Clone>(&mut self, tvar: &'a TVar<T>) -> T {
+        assert_not_held(tvar.address());
+        let (version, value) = tvar.inner.snapshot();
+        self.reads.push((tvar, version));
+        value
+    }
+}
+
+/// A tuple of `TVar`s that get written by an `atomically()` transaction.
+pub trait WriteSet<'a> {
+    /// The copies of the `TVar`s values.
+    type Values;
+    /// The versions of the `TVar`s, when the copies were made.
+    type Versions;
+    type Guards;
+
+    /// Creates copies of the current values, alongside their versions.
+    fn snapshot(&self) -> (Self::Versions, Self::Values);
+    /// The version, from `versions`, of the `TVar` at `address`.
+    fn version_of(&self, versions: &Self::Versions, address: usize) -> Option<u64>;
+    /// Whether the `TVar`s versions are still the same as `versions`.
+    fn unchanged(&self, versions: &Self::Versions) -> bool;
+    /// Exclusively accesses the `TVar`s, only if their versions are still
+    /// the same as `versions`.
+    ///
+    /// Never blocks, and instead returns whether a `TVar` was changed or
+    /// is busy.
+    fn try_lock_unchanged(&self, versions: &Self::Versions) -> Result<Self::Guards, Conflict>;
+    /// Replaces the `TVar`s values, increasing their versions.
+    fn replace(guards: &mut Self::Guards, values: Self::Values);
+    /// Appends the addresses of the `TVar`s.
+    fn cells(&self, cells: &mut Vec<usize>);
+}
+
+impl<'a> WriteSet<'a> for () {
+    type Values = ();
+    type Versions = ();
+    type Guards = ();
+
+    fn snapshot(&self) -> ((), ()) {
+        ((), ())
+    }
+
+    fn version_of(&self, _versions: &(), _address: usize) -> Option<u64> {
+        None
+    }
+
+    fn unchanged(&self, _versions: &()) -> bool {
+        true
+    }
+
+    fn try_lock_unchanged(&self, _versions: &()) -> Result<(), Conflict> {
+        Ok(())
+    }
+
+    fn replace(_guards: &mut (), _values: ()) {}
+
+    fn cells(&self, _cells: &mut Vec<usize>) {}
+}
+
+macro_rules! write_set_impls {
+    ( @version $n:tt ) => {
+        u64
+    };
+    ( $( $n:tt ),+ ) => {
+        paste! {
+            impl<
+                'a,
+                $( [<T $n>]: Clone, )+
+            > WriteSet<'a> for (
+                $( &'a TVar<[<T $n>]>, )+
+            ) {
+                type Values = (
+                    $( [<T $n>], )+
+                );
+                type Versions = (
+                    $( write_set_impls!(@version $n), )+
+                );
+                type Guards = (
+                    $( VersionedGuard<'a, [<T $n>]>, )+
+                );
+
+                fn snapshot(&self) -> (Self::Versions, Self::Values) {
+                    let (
+                        $( [<w $n>], )+
+                    ) = *self;
+                    $( let ([<s $n>], [<v $n>]) = [<w $n>].inner.snapshot(); )+
+                    (
+                        ( $( [<s $n>], )+ ),
+                        ( $( [<v $n>], )+ ),
+                    )
+                }
+
+                fn version_of(&self, versions: &Self::Versions, address: usize) -> Option<u64> {
+                    let (
+                        $( [<w $n>], )+
+                    ) = *self;
+                    let (
+                        $( [<s $n>], )+
+                    ) = *versions;
+                    $(
+                        if [<w $n>].address() == address {
+                            return Some([<s $n>]);
+                        }
+                    )+
+                    None
+                }
+
+                fn unchanged(&self, versions: &Self::Versions) -> bool {
+                    let (
+                        $( [<w $n>], )+
+                    ) = *self;
+                    let (
+                        $( [<s $n>], )+
+                    ) = *versions;
+                    $( [<w $n>].version() == [<s $n>] && )+ true
+                }
+
+                fn try_lock_unchanged(&self, versions: &Self::Versions) -> Result<Self::Guards, Conflict> {
+                    let (
+                        $( [<w $n>], )+
+                    ) = *self;
+                    let (
+                        $( [<s $n>], )+
+                    ) = *versions;
+                    // never blocks, so the locking order doesn't matter.
+                    // on failures, the already acquired guards are released
+                    Ok((
+                        $( [<w $n>].inner.try_lock_version([<s $n>])?, )+
+                    ))
+                }
+
+                fn replace(guards: &mut Self::Guards, values: Self::Values) {
+                    let (
+                        $( [<g $n>], )+
+                    ) = guards;
+                    let (
+                        $( [<v $n>], )+
+                    ) = values;
+                    $( [<g $n>].replace([<v $n>]); )+
+                }
+
+                fn cells(&self, cells: &mut Vec<usize>) {
+                    let (
+                        $( [<w $n>], )+
+                    ) = *self;
+                    $( cells.push([<w $n>].address()); )+
+                }
+            }
+        }
+    };
+}
+
+write_set_impls! {1}
+write_set_impls! {1, 2}
+write_set_impls! {1, 2, 3}
+write_set_impls! {1, 2, 3, 4}
+write_set_impls! {1, 2, 3, 4, 5}
+write_set_impls! {1, 2, 3, 4, 5, 6}
+write_set_impls! {1, 2, 3, 4, 5, 6, 7}
+write_set_impls! {1, 2, 3, 4, 5, 6, 7, 8}
+write_set_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9}
+write_set_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10}
+write_set_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11}
+write_set_impls! {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12}
+
+/// The `TVar`s written by an `atomically()` transaction.
+///
+/// See `prepare_atomically()`.
+pub struct Writes<'a, W> {
+    set: W,
+    _a: PhantomData<&'a ()>,
+}
+
+impl<'a, W: WriteSet<'a>> TakeOwned<Token<'a, W::Values>, target::Token> for Writes<'a, W> {
+    /// # Safety
+    ///
+    /// It is assumed that the caller has correctly used this method.
+    unsafe fn take_owned(self) -> Token<'a, W::Values> {
+        // the `TVar`s are shared, so the transaction is what holds their
+        // exclusive access
+        Token(PhantomData)
+    }
+}
+
+/// A run of an `atomically()` closure.
+///
+/// Holds the modified copies of the written `TVar`s and, once the run is
+/// validated, the guards that keep all of the observed `TVar`s unchanged
+/// until the commit.
+pub struct Attempt<'a, W: WriteSet<'a>> {
+    versions: W::Versions,
+    values: W::Values,
+    guards: Option<W::Guards>,
+    held: Vec<Box<dyn Held + 'a>>,
+    holding: Option<Holding>,
+}
+
+/// Whether all of the reads are still at their recorded versions.
+fn reads_unchanged<'a, W: WriteSet<'a>>(writes: &W, versions: &W::Versions, tx: &Tx<'a>) -> bool {
+    tx.reads.iter().all(|&(read, version)| {
+        let current = writes
+            .version_of(versions, read.address())
+            .unwrap_or_else(|| read.version());
+        current == version
+    })
+}
+
+/// Exclusively accesses all of the written and read `TVar`s, only if
+/// they are still at the versions that the run observed.
+#[allow(clippy::type_complexity)]
+fn lock_observed<'a, W: WriteSet<'a>>(
+    writes: &W,
+    versions: &W::Versions,
+    tx: &Tx<'a>,
+) -> Result<(W::Guards, Vec<Box<dyn Held + 'a>>, Holding), Conflict> {
+    let guards = writes.try_lock_unchanged(versions)?;
+
+    // holds the read `TVar`s, so they can't change until the commit
+    // is finished
+    let mut held: Vec<(usize, u64, Box<dyn Held + 'a>)> = Vec::new();
+    for &(read, version) in tx.reads.iter() {
+        let address = read.address();
+        // on failures, the already acquired guards are released
+        if let Some(written) = writes.version_of(versions, address) {
+            // already held by the `guards`
+            if written != version {
+                return Err(Conflict::Changed);
+            }
+        } else if let Some(&(_, held_version, _)) = held
+            .iter()
+            .find(|(held_address, _, _)| *held_address == address)
+        {
+            // read more than once
+            if held_version != version {
+                return Err(Conflict::Changed);
+            }
+        } else {
+            let guard = read.try_lock_version(version)?;
+            held.push((address, version, guard));
+        }
+    }
+    let mut addresses = Vec::new();
+    writes.cells(&mut addresses);
+    addresses.extend(held.iter().map(|(address, _, _)| *address));
+    let holding = Holding::new(addresses);
+    let held = held.into_iter().map(|(_, _, guard)| guard).collect();
+    Ok((guards, held, holding))
+}
+
+impl<'a, W, F, O, E> PartialApply<W::Values, F, O, E>
+    for Prepared<Writes<'a, W>, W::Values, F, E, mode::Atomically>
+where
+    W: WriteSet<'a>,
+    F: FnMut(&mut Tx<'a>, &mut W::Values) -> Result<O, E>,
+{
+    type Next = Attempt<'a, W>;
+
+    /// # Panics
+    ///
+    /// Panics if this thread holds any of the written `TVar`s, ie. if an
+    /// earlier member of the same transaction also accesses them.
+    fn get_next(&self) -> Attempt<'a, W> {
+        let mut cells = Vec::new();
+        self.inner.set.cells(&mut cells);
+        cells.into_iter().for_each(assert_not_held);
+        let (versions, values) = self.inner.set.snapshot();
+        Attempt {
+            versions,
+            values,
+            guards: None,
+            held: Vec::new(),
+            holding: None,
+        }
+    }
+
+    /// Runs the closure until a run is validated, or until it returns
+    /// an `Err` from a consistent run.
+    ///
+    /// The original values are never changed, but on `Ok` they are held
+    /// until the `replace()`.
+    fn modify_next(&self, mut next: Attempt<'a, W>, mut f: F) -> Result<(O, Attempt<'a, W>), E> {
+        let writes = &self.inner.set;
+        // the copies may have been made before an earlier member of the
+        // same transaction held the `TVar`s
+        let mut cells = Vec::new();
+        writes.cells(&mut cells);
+        cells.into_iter().for_each(assert_not_held);
+        let mut pause = Pause::new();
+        loop {
+            let mut tx = Tx { reads: Vec::new() };
+            match (f)(&mut tx, &mut next.values) {
+                Ok(o) => loop {
+                    match lock_observed(writes, &next.versions, &tx) {
+                        Ok((guards, held, holding)) => {
+                            next.guards = Some(guards);
+                            next.held = held;
+                            next.holding = Some(holding);
+                            return Ok((o, next));
+                        }
+                        // the run may still be committed
+                        Err(Conflict::Busy) => pause.wait(),
+                        Err(Conflict::Changed) => break,
+                    }
+                },
+                Err(e) => {
+                    if writes.unchanged(&next.versions)
+                        && reads_unchanged(writes, &next.versions, &tx)
+                    {
+                        return Err(e);
+                    }
+                    // the error may come from an inconsistent run
+                }
+            }
+            // conflict, re-runs with new copies
+            pause.wait();
+            next = self.get_next();
+        }
+    }
+
+    fn replace(&mut self, next: Attempt<'a, W>) {
+        let Attempt {
+            values,
+            guards,
+            held,
+            holding,
+            ..
+        } = next;
+        let mut guards = guards.expect("the run was validated by `modify_next()`");
+        W::replace(&mut guards, values);
+        drop(guards);
+        drop(held);
+        drop(holding);
+    }
+}
+
+impl<'v, 'a, W, F, O, E> PartialPending<'v, W::Values, F, O, E>
+    for Prepared<Writes<'a, W>, W::Values, F, E, mode::Atomically>
+where
+    Self: PartialApply<W::Values, F, O, E, Next = Attempt<'a, W>>,
+    W: WriteSet<'a>,
+{
+    /// The copies of the `TVar`s values, as a tuple.
+    type View = &'v W::Values;
+
+    fn pending(&'v self, next: &'v Attempt<'a, W>) -> &'v W::Values {
+        &next.values
+    }
+}
+
+/// Defines a transaction over the `writes` `TVar`s, as a `Prepared`
+/// modification that can be chained with other ones.
+///
+/// Once it's run is validated, the observed `TVar`s are held until the
+/// commit, so the later members of the same transaction can't access
+/// them. Such an access panics instead of blocking forever.
+///
+/// `f` receives copies of the `writes` values, and any other `TVar` can
+/// be read with `Tx::read()`.
+///
+/// During `apply`, all of the written and read `TVar`s must still be at
+/// the versions that `f` observed, otherwise `f` is re-run with new
+/// copies. A run of `f` may observe `TVar`s that were changed in between
+/// their reads, but such a run is never committed. Also, an `Err` is
+/// only returned if the observed `TVar`s were still unchanged after `f`
+/// returned it, otherwise `f` is re-run.
+///
+/// # Safety
+///
+/// See `OneMut::prepare()`.
+///
+/// # Panics
+///
+/// Panics if the same `TVar` is present more than once in `writes`, as
+/// it's second access would always conflict with the first one. During
+/// `apply`, panics if an earlier member of the same transaction holds any
+/// of the `TVar`s that this one accesses.
+#[allow(clippy::type_complexity)]
+pub unsafe fn prepare_atomically<'a, W, F, O, E>(
+    writes: W,
+    f: F,
+) -> Prepared<Writes<'a, W>, W::Values, F, E, mode::Atomically>
+where
+    W: WriteSet<'a>,
+    F: FnMut(&mut Tx<'a>, &mut W::Values) -> Result<O, E>,
+{
+    unchecked_prepare_atomically(writes, f)
+}
+
+/// See `prepare_atomically()`.
+#[allow(clippy::type_complexity)]
+pub fn unchecked_prepare_atomically<'a, W, F, O, E>(
+    writes: W,
+    f: F,
+) -> Prepared<Writes<'a, W>, W::Values, F, E, mode::Atomically>
+where
+    W: WriteSet<'a>,
+    F: FnMut(&mut Tx<'a>, &mut W::Values) -> Result<O, E>,
+{
+    let mut cells = Vec::new();
+    writes.cells(&mut cells);
+    cells.sort_unstable();
+    for pair in cells.windows(2) {
+        assert!(
+            pair[0] != pair[1],
+            "the same TVar cannot be written more than once in a transaction"
+        );
+    }
+    let writes = Writes {
+        set: writes,
+        _a: PhantomData,
+    };
+    Prepared::with_mode(writes, f)
+}
+
+/// Runs `f` as a transaction over the `writes` `TVar`s.
+///
+/// See `prepare_atomically()` for how `f` is run and validated, and
+/// `OneMut::prepare()` for the `Result` signaling of `f`.
+///
+/// On `Ok`, the copies are replaced into all of the `writes` `TVar`s,
+/// and a `ConsumedToken` for all of them is returned.
+///
+/// # Panics
+///
+/// Panics if the same `TVar` is present more than once in `writes`.
+pub fn atomically<'a, W, F, O, E>(writes: W, f: F) -> AllOrNone<'a, O, E, W::Values>
+where
+    W: WriteSet<'a> + 'a,
+    F: FnMut(&mut Tx<'a>, &mut W::Values) -> Result<O, E> + Clone + 'a,
+    E: 'a,
+{
+    unchecked_prepare_atomically(writes, f).apply()
+}
@@ -6,12 +6,12 @@ use std::marker::PhantomData;
 ///
 /// See also `OneMut`.
 #[derive(Debug)]
-pub struct Token<'t, T>(pub(crate) PhantomData<&'t T>);
+pub struct Token<'t, T: ?Sized>(pub(crate) PhantomData<&'t T>);
 
 /// A tag value that can be moved, and indicates that `T` will
 /// no longer be able to be modified.
 #[derive(Debug)]
-pub struct ConsumedToken<'t, T>(PhantomData<&'t T>);
+pub struct ConsumedToken<'t, T: ?Sized>(PhantomData<&'t T>);
 
 /// A tag value related to containers.
 ///
@@ -57,7 +57,9 @@ impl<'t, T> Token<'t, T> {
     pub fn then<'t2, 'tboth, T2>(self, _token2: Token<'t2, T2>) -> Token<'tboth, (T, T2)> {
         Token(PhantomData)
     }
+}
 
+impl<'t, T: ?Sized> Token<'t, T> {
     /// Consumes the token.
     pub fn consume(self) -> ConsumedToken<'t, T> {
         self.into()
@@ -84,19 +86,24 @@ where
 }
 
 /// Consumes a Token.
-impl<'t, T> From<Token<'t, T>> for ConsumedToken<'t, T> {
+impl<'t, T: ?Sized> From<Token<'t, T>> for ConsumedToken<'t, T> {
     fn from(token: Token<'t, T>) -> Self {
         ConsumedToken(token.0)
     }
 }
 
+/// # Safety
+/// (entirely logical)
+///
+/// You must guarantee that converting `T` into this token is
+/// logically correct.
 pub unsafe trait UncheckedFrom<T>: Sized {
     /// Performs the conversion.
     fn unchecked_from(_: T) -> Self;
 }
 
-unsafe impl<'t, T> UncheckedFrom<OneMut<'t, T>> for Token<'t, T> {
-    fn unchecked_from(t: OneMut<'t, T>) -> Self {
+unsafe impl<'t, T, M> UncheckedFrom<OneMut<'t, T, M>> for Token<'t, T> {
+    fn unchecked_from(t: OneMut<'t, T, M>) -> Self {
         t.unchecked_token()
     }
 }
@@ -107,8 +114,8 @@ unsafe impl<'t, T> UncheckedFrom<Token<'t, T>> for Token<'t, T> {
     }
 }
 
-unsafe impl<'t, T> UncheckedFrom<OneMut<'t, T>> for ConsumedToken<'t, T> {
-    fn unchecked_from(t: OneMut<'t, T>) -> Self {
+unsafe impl<'t, T, M> UncheckedFrom<OneMut<'t, T, M>> for ConsumedToken<'t, T> {
+    fn unchecked_from(t: OneMut<'t, T, M>) -> Self {
         t.unchecked_consume()
     }
 }
@@ -119,7 +126,7 @@ unsafe impl<'t, T> UncheckedFrom<ConsumedToken<'t, T>> for ConsumedToken<'t, T>
     }
 }
 
-impl<'t, T> Take<T, target::Type> for OneMut<'t, T> {
+impl<'t, T, M> Take<T, target::Type> for OneMut<'t, T, M> {
     fn take_ref(&self) -> &T {
         self.inner
     }
@@ -129,7 +136,7 @@ impl<'t, T> Take<T, target::Type> for OneMut<'t, T> {
     }
 }
 
-impl<'t, T> Take<Token<'t, T>, target::Token> for OneMut<'t, T> {
+impl<'t, T, M> Take<Token<'t, T>, target::Token> for OneMut<'t, T, M> {
     fn take_ref(&self) -> &Token<'t, T> {
         &self.token
     }
@@ -139,8 +146,136 @@ impl<'t, T> Take<Token<'t, T>, target::Token> for OneMut<'t, T> {
     }
 }
 
-impl<'t, T> TakeOwned<Token<'t, T>, target::Token> for OneMut<'t, T> {
+impl<'t, T, M> TakeOwned<Token<'t, T>, target::Token> for OneMut<'t, T, M> {
     unsafe fn take_owned(self) -> Token<'t, T> {
         self.token
     }
 }
+
+/// Identifies a member of a `DynTransaction`, and it's token in the
+/// `TokenSet` or `ConsumedTokenSet` that the transaction returns.
+///
+/// See `DynTransaction::push()`.
+#[derive(Debug)]
+pub struct TokenKey<T: ?Sized> {
+    set: usize,
+    index: usize,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T: ?Sized> Clone for TokenKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for TokenKey<T> {}
+
+impl<T: ?Sized> TokenKey<T> {
+    pub(crate) fn new(set: usize, index: usize) -> Self {
+        Self {
+            set,
+            index,
+            _t: PhantomData,
+        }
+    }
+}
+
+/// A runtime-sized set of `Token`s, of heterogeneous `T`s.
+///
+/// Each `Token` is identified by the `TokenKey` of it's member, so
+/// members of the same `T` are told apart.
+///
+/// See also `DynTransaction`.
+#[derive(Debug, Default)]
+pub struct TokenSet<'t> {
+    set: usize,
+    present: Vec<bool>,
+    _t: PhantomData<&'t ()>,
+}
+
+/// A runtime-sized set of `ConsumedToken`s, of heterogeneous `T`s.
+///
+/// See also `TokenSet`.
+#[derive(Debug, Default)]
+pub struct ConsumedTokenSet<'t> {
+    set: usize,
+    present: Vec<bool>,
+    _t: PhantomData<&'t ()>,
+}
+
+impl<'t> TokenSet<'t> {
+    /// A set with the `Token`s of all of the `len` members of `set`.
+    pub(crate) fn new(set: usize, len: usize) -> Self {
+        Self {
+            set,
+            present: vec![true; len],
+            _t: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.present.iter().filter(|p| **p).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the set has the `Token` of `key`.
+    pub fn contains<T: ?Sized>(&self, key: &TokenKey<T>) -> bool {
+        key.set == self.set && self.present.get(key.index) == Some(&true)
+    }
+
+    /// Extracts the `Token` of `key`, if the set has it.
+    pub fn take<T: ?Sized>(&mut self, key: &TokenKey<T>) -> Option<Token<'t, T>> {
+        if !self.contains(key) {
+            return None;
+        }
+        self.present[key.index] = false;
+        Some(Token(PhantomData))
+    }
+
+    /// Consumes all of the tokens.
+    pub fn consume(self) -> ConsumedTokenSet<'t> {
+        ConsumedTokenSet {
+            set: self.set,
+            present: self.present,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<'t> ConsumedTokenSet<'t> {
+    /// A set with the `ConsumedToken`s of all of the `len` members of
+    /// `set`.
+    pub(crate) fn new(set: usize, len: usize) -> Self {
+        Self {
+            set,
+            present: vec![true; len],
+            _t: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.present.iter().filter(|p| **p).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the set has the `ConsumedToken` of `key`.
+    pub fn contains<T: ?Sized>(&self, key: &TokenKey<T>) -> bool {
+        key.set == self.set && self.present.get(key.index) == Some(&true)
+    }
+
+    /// Extracts the `ConsumedToken` of `key`, if the set has it.
+    pub fn take<T: ?Sized>(&mut self, key: &TokenKey<T>) -> Option<ConsumedToken<'t, T>> {
+        if !self.contains(key) {
+            return None;
+        }
+        self.present[key.index] = false;
+        Some(ConsumedToken(PhantomData))
+    }
+}
@@ -0,0 +1,191 @@
+use super::{target, ConsumedTokenSet, PartialApply, Take, TakeOwned, Token, TokenKey, TokenSet};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tells apart the `TokenKey`s of different transactions.
+static NEXT_SET: AtomicUsize = AtomicUsize::new(0);
+
+/// A modification with dynamic dispatch, as a member of a
+/// `DynTransaction`.
+///
+/// # Safety
+///
+/// `modify()` must only modify a copy of `T`, and `commit()` must only
+/// replace `T` with the copy that was modified by the last successful
+/// `modify()`. `finish()` must consume the member's `Token`.
+pub unsafe trait DynApply<'t, O, E> {
+    /// Modifies a copy of `T`, keeping it for the `commit()`.
+    fn modify(&mut self) -> Result<O, E>;
+
+    /// Replaces the original `T` with the modified copy.
+    fn commit(&mut self);
+
+    /// Consumes the member and it's `Token`.
+    fn finish(self: Box<Self>);
+}
+
+/// A `Prepared` modification, staged for a `DynTransaction`.
+struct Staged<A, T, F, O, E>
+where
+    A: PartialApply<T, F, O, E>,
+{
+    a: A,
+    next: Option<A::Next>,
+    _t: PhantomData<(T, F, O, E)>,
+}
+
+unsafe impl<'t, A, T, F, O, E> DynApply<'t, O, E> for Staged<A, T, F, O, E>
+where
+    A: PartialApply<T, F, O, E>
+        + Take<F, target::Function>
+        + TakeOwned<Token<'t, T>, target::Token>,
+    F: Clone,
+    T: 't,
+{
+    fn modify(&mut self) -> Result<O, E> {
+        let next = self.a.get_next();
+        let f: &F = self.a.take_ref();
+        let (o, next) = self.a.modify_next(next, f.clone())?;
+        self.next = Some(next);
+        Ok(o)
+    }
+
+    fn commit(&mut self) {
+        if let Some(next) = self.next.take() {
+            self.a.replace(next);
+        }
+    }
+
+    fn finish(self: Box<Self>) {
+        // Safety:
+        //
+        // the member is consumed, preventing further mutations
+        let _t: Token<'t, T> = unsafe { self.a.take_owned() };
+    }
+}
+
+/// Container of a runtime-sized amount of modifications, of
+/// heterogeneous `T`s.
+///
+/// This is the dynamic sibling of `Chain`: during `apply`, copies of all
+/// of the members are modified, in order, and only after all of the
+/// modifications were successful, the original values are replaced.
+///
+/// All of the members share the output type `O` and the error type `E`.
+/// A member is a `Prepared` modification, or anything else that gives
+/// out it's closure by `Take`; a `Chain` is not a member, but each of
+/// it's members can be pushed instead.
+pub struct DynTransaction<'t, O, E> {
+    set: usize,
+    members: Vec<Box<dyn DynApply<'t, O, E> + 't>>,
+}
+
+impl<'t, O, E> Default for DynTransaction<'t, O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'t, O, E> DynTransaction<'t, O, E> {
+    pub fn new() -> Self {
+        Self {
+            set: NEXT_SET.fetch_add(1, Ordering::Relaxed),
+            members: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Adds a modification into the transaction.
+    ///
+    /// The returned key identifies the member's token in the sets
+    /// returned by `apply()`.
+    pub fn push<A, T, F>(&mut self, a: A) -> TokenKey<T>
+    where
+        A: PartialApply<T, F, O, E>
+            + Take<F, target::Function>
+            + TakeOwned<Token<'t, T>, target::Token>
+            + 't,
+        F: Clone + 't,
+        T: 't,
+        O: 't,
+        E: 't,
+    {
+        let key = TokenKey::new(self.set, self.members.len());
+        self.members.push(Box::new(Staged {
+            a,
+            next: None,
+            _t: PhantomData,
+        }));
+        key
+    }
+
+    /// Same as `push()`, but by value, and without a `TokenKey`.
+    pub fn with<A, T, F>(mut self, a: A) -> Self
+    where
+        A: PartialApply<T, F, O, E>
+            + Take<F, target::Function>
+            + TakeOwned<Token<'t, T>, target::Token>
+            + 't,
+        F: Clone + 't,
+        T: 't,
+        O: 't,
+        E: 't,
+    {
+        let _key = self.push(a);
+        self
+    }
+
+    /// Adds an already boxed modification into the transaction.
+    ///
+    /// As it's `T` is unknown, it's token can't be taken out of the sets
+    /// returned by `apply()`.
+    pub fn push_boxed(&mut self, member: Box<dyn DynApply<'t, O, E> + 't>) {
+        self.members.push(member);
+    }
+
+    /// Applies all of the modifications, all-or-nothing.
+    ///
+    /// On success, returns the outputs of the members, in order, and the
+    /// consumed tokens of all of them. On the first error, nothing is
+    /// replaced and the tokens of all of them are returned.
+    ///
+    /// The tokens are taken out of the sets by the `TokenKey`s that
+    /// `push()` returned.
+    #[allow(clippy::type_complexity)]
+    pub fn apply(self) -> Result<(Vec<O>, ConsumedTokenSet<'t>), (E, TokenSet<'t>)> {
+        let set = self.set;
+        let mut members = self.members;
+        let len = members.len();
+        let mut outputs = Vec::with_capacity(members.len());
+
+        // modify all copies, in order
+        for member in members.iter_mut() {
+            match member.modify() {
+                Ok(o) => outputs.push(o),
+                Err(e) => {
+                    // this is indicating that the mutation failed,
+                    // and also preventing further mutations
+                    members.into_iter().for_each(|m| m.finish());
+                    return Err((e, TokenSet::new(set, len)));
+                }
+            }
+        }
+
+        // only replace after all modifications were successful
+        for member in members.iter_mut() {
+            member.commit();
+        }
+
+        // this is indicating that the mutation was successful,
+        // and also preventing further mutations
+        members.into_iter().for_each(|m| m.finish());
+        Ok((outputs, ConsumedTokenSet::new(set, len)))
+    }
+}
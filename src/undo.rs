@@ -0,0 +1,83 @@
+use crate::{target, AllOrNone, ConsumedToken, TakeOwned, Token};
+
+/// Replaces the original `T` with a modified copy of `T`, while
+/// giving back the old value of the original `T`.
+///
+/// This is the counterpart of `PartialApply::replace` for when the
+/// old value should be kept instead of discarded.
+pub trait PartialSwap<T> {
+    /// Replaces the original `T` with `next`, returning the old original `T`.
+    fn swap(&mut self, next: T) -> T;
+}
+
+/// Holds the old values of `T` that got replaced by a
+/// `ApplyReversible::commit_reversible()`.
+///
+/// While the `Undo` exists, the originals stay mutably borrowed, so
+/// they can still be rolled back:
+///
+/// - `rollback()` places the old values back into the originals,
+///   consuming the `ConsumedToken` and giving back a `Token`;
+/// - `finalize()` (or simply dropping the `Undo`) keeps the new values
+///   in place and discards the rollback possibility.
+pub struct Undo<A, T> {
+    inner: A,
+    old: T,
+}
+
+impl<A, T> Undo<A, T> {
+    pub(crate) fn new(inner: A, old: T) -> Self {
+        Self { inner, old }
+    }
+
+    /// Shared access into the old values of `T`.
+    pub fn old(&self) -> &T {
+        &self.old
+    }
+
+    /// Places the old values back into the original `T`.
+    ///
+    /// The `ConsumedToken` that indicated the change is consumed,
+    /// and the `Token` is given back, indicating that `T` is back
+    /// to it's original state.
+    pub fn rollback<'t>(mut self, _consumed: ConsumedToken<'t, T>) -> Token<'t, T>
+    where
+        A: PartialSwap<T> + TakeOwned<Token<'t, T>, target::Token>,
+    {
+        let _new = self.inner.swap(self.old);
+
+        // Safety:
+        //
+        // the original values were completely restored, so
+        // this is indicating that `T` is unchanged
+        unsafe { self.inner.take_owned() }
+    }
+
+    /// Keeps the new values in place, returning the old values of `T`.
+    pub fn finalize(self) -> T {
+        self.old
+    }
+}
+
+/// # Safety
+///
+/// Has the same requirements as `Apply`, and additionally, in the `Ok`
+/// case, the `Undo` must hold the complete old value of the original `T`.
+///
+///
+/// Trait types:
+///
+/// - `T` is the protected type.
+/// - `F` is the scoped closure that will mut access `T`.
+/// - `O` is `F`'s `Ok` return type.
+/// - `E` is `F`'s `Err` return type.
+pub unsafe trait ApplyReversible<'t, T, F, O, E>: Sized {
+    /// Copies `T`, modifies it, and then swaps it with the
+    /// original `T`.
+    ///
+    /// - `Ok` implies the original `T` got completely modified,
+    ///   and the `Undo` holds it's old value,
+    /// - `Err` implies the original `T` is untouched.
+    #[allow(clippy::type_complexity)]
+    fn commit_reversible(self) -> AllOrNone<'t, (O, Undo<Self, T>), E, T>;
+}
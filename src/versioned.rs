@@ -0,0 +1,418 @@
+use crate::{AllOrNone, Chain, ConsumedToken, Token};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::marker::PhantomData;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use std::time::Duration;
+
+/// A shared `T` that is modified by optimistic transactions.
+///
+/// The `T` is kept alongside a version counter, which is increased
+/// on every commit.
+///
+/// A transaction takes a snapshot of `T` (and of it's version) under a
+/// read lock, then modifies the snapshot while no lock is held, and
+/// then commits it under the write lock only if the version is still
+/// unchanged. Otherwise the transaction is retried from a new snapshot.
+///
+/// Unlike the `lock` module, a poisoned lock is recovered, as `T` is only
+/// ever replaced as a whole, and so it can't be left partially modified
+/// by a panic. The replaced `T` is only dropped after the lock is
+/// released, so it's `Drop` can't poison the lock either.
+///
+/// See `Versioned::prepare()`.
+pub struct Versioned<T> {
+    slot: RwLock<Slot<T>>,
+}
+
+struct Slot<T> {
+    version: u64,
+    value: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            slot: RwLock::new(Slot { version: 0, value }),
+        }
+    }
+
+    /// How many commits happened into `T`.
+    pub fn version(&self) -> u64 {
+        self.read().version
+    }
+
+    /// Creates a copy of the current `T`.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.snapshot().1
+    }
+
+    /// Creates a copy of the current `T`, alongside it's version.
+    pub fn snapshot(&self) -> (u64, T)
+    where
+        T: Clone,
+    {
+        let slot = self.read();
+        (slot.version, slot.value.clone())
+    }
+
+    pub fn into_inner(self) -> T {
+        let slot = match self.slot.into_inner() {
+            Ok(slot) => slot,
+            // can't be partially modified, see `Versioned`
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        slot.value
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Slot<T>> {
+        match self.slot.read() {
+            Ok(slot) => slot,
+            Err(poisoned) => {
+                // can't be partially modified, see `Versioned`
+                self.slot.clear_poison();
+                poisoned.into_inner()
+            }
+        }
+    }
+
+    /// Exclusively accesses `T`, only if it's version is still
+    /// `version` and if it's not busy.
+    pub(crate) fn try_lock_version(&self, version: u64) -> Result<VersionedGuard<'_, T>, Conflict> {
+        let slot = match self.slot.try_write() {
+            Ok(slot) => slot,
+            Err(TryLockError::Poisoned(poisoned)) => {
+                // can't be partially modified, see `Versioned`
+                self.slot.clear_poison();
+                poisoned.into_inner()
+            }
+            Err(TryLockError::WouldBlock) => return Err(Conflict::Busy),
+        };
+        if slot.version == version {
+            Ok(VersionedGuard {
+                slot,
+                replaced: None,
+            })
+        } else {
+            Err(Conflict::Changed)
+        }
+    }
+
+    /// Defines how `T` should be mutated, given an `Ok` response.
+    ///
+    /// See `OneMut::prepare()` for the `Result` signaling.
+    /// The closure may be executed more than once, as each conflicting
+    /// commit makes the transaction be retried, so it should not have
+    /// side effects besides the mutation into `T`.
+    ///
+    /// # Safety
+    ///
+    /// See `OneMut::prepare()`.
+    pub unsafe fn prepare<F, E>(&self, f: F) -> VersionedPrepared<'_, T, F, E> {
+        self.unchecked_prepare(f)
+    }
+
+    /// See `Versioned::prepare()`.
+    pub fn unchecked_prepare<F, E>(&self, f: F) -> VersionedPrepared<'_, T, F, E> {
+        VersionedPrepared {
+            inner: self,
+            f,
+            _err: PhantomData,
+        }
+    }
+}
+
+/// Holds a single scoped modification into a snapshot of a
+/// `Versioned<T>`.
+///
+/// See `Optimistic::transact()`.
+pub struct VersionedPrepared<'t, T, F, E> {
+    inner: &'t Versioned<T>,
+    f: F,
+    _err: PhantomData<E>,
+}
+
+impl<'t, T, F, E> VersionedPrepared<'t, T, F, E> {
+    /// Chains this modification with another one, so that both are
+    /// committed in the same transaction.
+    pub fn chain<A2>(self, a2: A2) -> Chain<Self, A2> {
+        Chain::new(self, a2)
+    }
+}
+
+/// Exclusive access into a `Versioned<T>`, held during a commit.
+pub struct VersionedGuard<'t, T> {
+    slot: RwLockWriteGuard<'t, Slot<T>>,
+    /// Dropped after the `slot`, so outside of the lock.
+    replaced: Option<T>,
+}
+
+impl<'t, T> VersionedGuard<'t, T> {
+    /// Replaces `T`, increasing it's version.
+    pub(crate) fn replace(&mut self, next: T) {
+        let slot = &mut self.slot;
+        slot.version += 1;
+        let replaced = std::mem::replace(&mut slot.value, next);
+        self.replaced = Some(replaced);
+    }
+}
+
+/// Why the original `T` could not be exclusively accessed for a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    /// `T` changed since the snapshot, so the modification is re-run
+    /// from a new snapshot.
+    Changed,
+    /// `T` is briefly accessed by another thread, such as by a snapshot,
+    /// so only the locking is retried, keeping the modified copy.
+    Busy,
+}
+
+/// Indicates that the same cell is present more than once in a
+/// transaction, as it's second access would always conflict with the
+/// first one.
+///
+/// `index` is the position, in the chain, of the repeated cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepeatedCellError {
+    pub index: usize,
+}
+
+/// The first tries spin, then they yield, and then they sleep.
+const SPIN_TRIES: u32 = 6;
+const YIELD_TRIES: u32 = 10;
+/// The maximum sleep is `2^MAX_SLEEP_SHIFT` microseconds.
+const MAX_SLEEP_SHIFT: u32 = 10;
+
+/// Exponential backoff between conflicting tries, with random jitter,
+/// so that transactions that keep on conflicting with each other get
+/// out of step.
+pub(crate) struct Pause {
+    tries: u32,
+}
+
+impl Pause {
+    pub(crate) fn new() -> Self {
+        Self { tries: 0 }
+    }
+
+    pub(crate) fn wait(&mut self) {
+        if self.tries < SPIN_TRIES {
+            for _ in 0..(1 << self.tries) {
+                std::hint::spin_loop();
+            }
+        } else if self.tries < YIELD_TRIES {
+            std::thread::yield_now();
+        } else {
+            let max = 1u64 << (self.tries - YIELD_TRIES).min(MAX_SLEEP_SHIFT);
+            // each `RandomState` has new keys
+            let jitter = RandomState::new().build_hasher().finish() % max;
+            std::thread::sleep(Duration::from_micros(1 + jitter));
+        }
+        self.tries = self.tries.saturating_add(1);
+    }
+}
+
+/// Trait types:
+///
+/// - `T` is the protected type.
+/// - `F` is the scoped closure that will mut access `T`.
+/// - `O` is `F`'s `Ok` return type.
+/// - `E` is `F`'s `Err` return type.
+///
+/// # Safety
+///
+/// `try_lock_unchanged()` must only give out a `Guard` if `T` is still
+/// in the same state as it was on the `snapshot()` that created the
+/// `Stamp`, and the `Guard` must prevent any other changes into `T`
+/// until it's dropped.
+pub unsafe trait PartialOptimistic<T, F, O, E> {
+    /// Identifies the state of `T` when a snapshot was taken.
+    type Stamp;
+    /// Holds exclusive access into the original `T` for a commit.
+    type Guard;
+
+    /// Creates a copy of `T`, alongside it's `Stamp`.
+    fn snapshot(&self) -> (Self::Stamp, T);
+    /// Creates a copy of the closure, for a single modification try.
+    fn next_fn(&self) -> F;
+    /// Applies a modification into a `T` (presumably the copy of `T`).
+    fn modify_next(next: T, f: F) -> Result<(O, T), E>;
+    /// Exclusively accesses the original `T`, only if it was
+    /// unchanged since the `Stamp` was created.
+    ///
+    /// Never blocks, and instead returns whether the original `T` was
+    /// changed or is busy.
+    fn try_lock_unchanged(&self, stamp: &Self::Stamp) -> Result<Self::Guard, Conflict>;
+    /// Replaces the original `T` with the modified copy of `T`.
+    fn replace(guard: &mut Self::Guard, next: T);
+    /// Appends the addresses of the cells that get accessed by
+    /// `try_lock_unchanged()`.
+    fn cells(&self, cells: &mut Vec<usize>);
+}
+
+unsafe impl<'t, T, F, O, E> PartialOptimistic<T, F, O, E> for VersionedPrepared<'t, T, F, E>
+where
+    F: FnOnce(&mut T) -> Result<O, E> + Clone,
+    T: Clone,
+{
+    type Stamp = u64;
+    type Guard = VersionedGuard<'t, T>;
+
+    fn snapshot(&self) -> (u64, T) {
+        self.inner.snapshot()
+    }
+
+    fn next_fn(&self) -> F {
+        self.f.clone()
+    }
+
+    fn modify_next(mut next: T, f: F) -> Result<(O, T), E> {
+        let o = (f)(&mut next)?;
+        Ok((o, next))
+    }
+
+    fn try_lock_unchanged(&self, version: &u64) -> Result<Self::Guard, Conflict> {
+        self.inner.try_lock_version(*version)
+    }
+
+    fn replace(guard: &mut Self::Guard, next: T) {
+        guard.replace(next);
+    }
+
+    fn cells(&self, cells: &mut Vec<usize>) {
+        cells.push(self.inner as *const Versioned<T> as usize);
+    }
+}
+
+unsafe impl<A1, A2, T1, T2, F1, F2, O1, O2, E> PartialOptimistic<(T1, T2), (F1, F2), (O1, O2), E>
+    for Chain<A1, A2>
+where
+    A1: PartialOptimistic<T1, F1, O1, E>,
+    A2: PartialOptimistic<T2, F2, O2, E>,
+{
+    type Stamp = (A1::Stamp, A2::Stamp);
+    type Guard = (A1::Guard, A2::Guard);
+
+    fn snapshot(&self) -> (Self::Stamp, (T1, T2)) {
+        let (s1, t1) = self.a1.snapshot();
+        let (s2, t2) = self.a2.snapshot();
+        ((s1, s2), (t1, t2))
+    }
+
+    fn next_fn(&self) -> (F1, F2) {
+        (self.a1.next_fn(), self.a2.next_fn())
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn modify_next(
+        (next1, next2): (T1, T2),
+        (f1, f2): (F1, F2),
+    ) -> Result<((O1, O2), (T1, T2)), E> {
+        let (o1, next1) = A1::modify_next(next1, f1)?;
+        let (o2, next2) = A2::modify_next(next2, f2)?;
+        Ok(((o1, o2), (next1, next2)))
+    }
+
+    fn try_lock_unchanged(&self, (s1, s2): &Self::Stamp) -> Result<Self::Guard, Conflict> {
+        // never blocks, so the locking order doesn't matter.
+        // on failures, the already acquired guards are released
+        let g1 = self.a1.try_lock_unchanged(s1)?;
+        let g2 = self.a2.try_lock_unchanged(s2)?;
+        Ok((g1, g2))
+    }
+
+    fn replace((g1, g2): &mut Self::Guard, (next1, next2): (T1, T2)) {
+        A1::replace(g1, next1);
+        A2::replace(g2, next2);
+    }
+
+    fn cells(&self, cells: &mut Vec<usize>) {
+        self.a1.cells(cells);
+        self.a2.cells(cells);
+    }
+}
+
+/// Commits `PartialOptimistic` modifications, retrying on conflicts.
+///
+/// Trait types:
+///
+/// - `T` is the protected type.
+/// - `F` is the scoped closure that will mut access `T`.
+/// - `O` is `F`'s `Ok` return type.
+/// - `E` is `F`'s `Err` return type.
+pub trait Optimistic<'t, T, F, O, E> {
+    /// Snapshots `T`, modifies it, and then replaces it into the
+    /// original `T` if it's still unchanged. If `T` changed, this is
+    /// retried from a new snapshot, and if `T` is busy, only the commit
+    /// is retried. The tries are spaced by an exponential backoff.
+    ///
+    /// - `Ok` implies the original `T` got completely modified
+    ///   (ie. no incomplete modifications occurred),
+    /// - `Err` implies the original `T` is untouched.
+    ///
+    /// Fails with `RepeatedCellError`, before any modification, if the
+    /// same cell is present more than once.
+    fn transact(self) -> Result<AllOrNone<'t, O, E, T>, RepeatedCellError>;
+}
+
+/// Checks for repeated cells.
+fn distinct_cells<A: PartialOptimistic<T, F, O, E>, T, F, O, E>(
+    a: &A,
+) -> Result<(), RepeatedCellError> {
+    let mut cells = Vec::new();
+    a.cells(&mut cells);
+    let mut cells: Vec<(usize, usize)> = cells.into_iter().zip(0..).collect();
+    cells.sort_unstable();
+    match cells.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        Some(pair) => Err(RepeatedCellError { index: pair[1].1 }),
+        None => Ok(()),
+    }
+}
+
+impl<'t, A, T, F, O, E> Optimistic<'t, T, F, O, E> for A
+where
+    A: PartialOptimistic<T, F, O, E>,
+    T: 't,
+{
+    fn transact(self) -> Result<AllOrNone<'t, O, E, T>, RepeatedCellError> {
+        distinct_cells(&self)?;
+        let mut pause = Pause::new();
+        'snapshot: loop {
+            // no locks are held during the modification
+            let (stamp, next) = self.snapshot();
+            let (o, next) = match A::modify_next(next, self.next_fn()) {
+                Ok(v) => v,
+                Err(e) => {
+                    // the original `T` was never touched
+                    return Ok(Err((e, Token(PhantomData))));
+                }
+            };
+
+            let mut guard = loop {
+                match self.try_lock_unchanged(&stamp) {
+                    Ok(guard) => break guard,
+                    Err(Conflict::Busy) => {
+                        // the copy may still be committed
+                        pause.wait();
+                    }
+                    Err(Conflict::Changed) => {
+                        // retries from a new snapshot
+                        pause.wait();
+                        continue 'snapshot;
+                    }
+                }
+            };
+            // Safety:
+            //
+            // only replace after the modifications were successful,
+            // and while the original `T` is known to be unchanged.
+            // Also, after this, an `Ok` return is guaranteed
+            A::replace(&mut guard, next);
+            return Ok(Ok((o, ConsumedToken::from(Token(PhantomData)))));
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use onemut::{Apply, OneMut};
+
+#[derive(Clone, Debug)]
+struct A(pub Vec<u8>);
+
+#[derive(Clone, Debug)]
+struct B(pub Vec<usize>);
+
+#[derive(Clone, Debug)]
+struct C(pub usize);
+
+#[test]
+fn dependent() {
+    let mut a = A(vec![]);
+    let mut b = B(vec![]);
+    let mut c = C(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let cmut = OneMut::new(&mut c);
+    let (((id, len), ()), _toks) = amut
+        .unchecked_prepare(|a: &mut A| {
+            // allocates an id
+            a.0.push(7);
+            Ok::<_, ()>(a.0.len() - 1)
+        })
+        .and_then(bmut, |id: &usize, b: &mut B| {
+            b.0.push(*id);
+            Ok(b.0.len())
+        })
+        .and_then(cmut, |(id, len): &(usize, usize), c: &mut C| {
+            c.0 = id + len;
+            Ok(())
+        })
+        .apply()
+        .unwrap();
+    assert_eq!((id, len), (0, 1));
+
+    assert_eq!((a.0, b.0, c.0), (vec![7], vec![0], 1));
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut a = A(vec![]);
+    let mut b = B(vec![]);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let (_err, _toks) = amut
+        .unchecked_prepare(|a: &mut A| {
+            a.0.push(7);
+            Ok(a.0.len() - 1)
+        })
+        .and_then(bmut, |id: &usize, b: &mut B| {
+            if *id == 0 {
+                return Err(());
+            }
+            b.0.push(*id);
+            Ok(())
+        })
+        .apply()
+        .unwrap_err();
+
+    // the internal state is kept intact
+    assert!(a.0.is_empty());
+    assert!(b.0.is_empty());
+    // GOOD!
+}
@@ -0,0 +1,133 @@
+use onemut::{AtomicShared, Optimistic, RepeatedCellError, Versioned};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+struct A(pub u32);
+
+#[derive(Clone, Debug)]
+struct B(pub u32);
+
+#[test]
+fn concurrent_chain() {
+    let a = Arc::new(AtomicShared::new(A(0)));
+    let b = Arc::new(AtomicShared::new(B(0)));
+
+    let writers: Vec<_> = (0..4)
+        .map(|_| {
+            let a = Arc::clone(&a);
+            let b = Arc::clone(&b);
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    let a = a.unchecked_prepare(|a: &mut A| {
+                        a.0 += 1;
+                        Ok::<_, ()>(a.0)
+                    });
+                    let b = b.unchecked_prepare(|b: &mut B| {
+                        b.0 += 1;
+                        Ok(b.0)
+                    });
+                    let ((a0, b0), _toks) = a.chain(b).transact().unwrap().unwrap();
+                    assert_eq!(a0, b0);
+                }
+            })
+        })
+        .collect();
+    let reader = {
+        let a = Arc::clone(&a);
+        std::thread::spawn(move || {
+            let mut last = 0;
+            for _ in 0..1000 {
+                let current = a.load().0;
+                assert!(current >= last);
+                last = current;
+            }
+        })
+    };
+    for t in writers {
+        t.join().unwrap();
+    }
+    reader.join().unwrap();
+
+    assert_eq!(a.load().0, 400);
+    assert_eq!(b.load().0, 400);
+    // GOOD!
+}
+
+#[test]
+fn steady_readers() {
+    let a = Arc::new(AtomicShared::new(A(0)));
+    let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+    // the readers never stop loading while the writer commits
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let a = Arc::clone(&a);
+            let done = Arc::clone(&done);
+            std::thread::spawn(move || {
+                while !done.load(std::sync::atomic::Ordering::SeqCst) {
+                    let _current = a.load();
+                }
+            })
+        })
+        .collect();
+    for _ in 0..1000 {
+        let (_a0, _tok) = a
+            .unchecked_prepare(|a: &mut A| {
+                a.0 += 1;
+                Ok::<_, ()>(a.0)
+            })
+            .transact()
+            .unwrap()
+            .unwrap();
+    }
+    done.store(true, std::sync::atomic::Ordering::SeqCst);
+    for t in readers {
+        t.join().unwrap();
+    }
+
+    assert_eq!(a.load().0, 1000);
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let a = AtomicShared::new(A(0));
+    let b = Versioned::new(B(0));
+    let before = a.load();
+
+    let a_prep = a.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok(a.0)
+    });
+    let b_prep = b.unchecked_prepare(|b: &mut B| {
+        b.0 += 1;
+        Err::<u32, _>(())
+    });
+    let (_err, _toks) = a_prep.chain(b_prep).transact().unwrap().unwrap_err();
+
+    // the pointer was never swapped
+    assert!(Arc::ptr_eq(&before, &a.load()));
+    assert_eq!(b.get().0, 0);
+    // GOOD!
+}
+
+#[test]
+fn repeated() {
+    let a = AtomicShared::new(A(0));
+
+    let a1 = a.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    });
+    let a2 = a.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok(())
+    });
+    // the second claim would otherwise always fail
+    let err = a1.chain(a2).transact().unwrap_err();
+    assert_eq!(err, RepeatedCellError { index: 1 });
+
+    // nothing was modified
+    assert_eq!(a.load().0, 0);
+    // GOOD!
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::let_unit_value)]
+
 #[derive(Clone, Debug)]
 struct A(pub u8);
 
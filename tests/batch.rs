@@ -0,0 +1,62 @@
+use onemut::{batch, batch_array, ConsumedToken, OneMut};
+
+#[derive(Clone, Debug)]
+struct A(pub u8);
+
+#[test]
+fn rows() {
+    let mut rows = [A(0), A(1), A(2), A(3)];
+
+    let selected = rows.iter_mut().filter(|a| a.0 % 2 == 1).map(OneMut::new);
+    let (outputs, _tok): (_, ConsumedToken<[A]>) = batch(selected, |i, a: &mut A| {
+        a.0 += 10;
+        Ok::<_, ()>(i)
+    })
+    .unwrap();
+
+    assert_eq!(outputs, vec![0, 1]);
+    let rows: Vec<u8> = rows.iter().map(|a| a.0).collect();
+    assert_eq!(rows, vec![0, 11, 2, 13]);
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut rows = [A(0), A(1), A(2), A(3)];
+
+    let all = rows.iter_mut().map(OneMut::new);
+    let (_err, _tok) = batch(all, |_i, a: &mut A| {
+        if a.0 == 2 {
+            return Err(());
+        }
+        a.0 += 10;
+        Ok(())
+    })
+    .unwrap_err();
+
+    let rows: Vec<u8> = rows.iter().map(|a| a.0).collect();
+    assert_eq!(rows, vec![0, 1, 2, 3]);
+    // GOOD!
+}
+
+#[test]
+fn array() {
+    let mut a0 = A(0);
+    let mut a1 = A(1);
+    let mut a2 = A(2);
+
+    let ones = [
+        OneMut::new(&mut a0),
+        OneMut::new(&mut a1),
+        OneMut::new(&mut a2),
+    ];
+    let (outputs, _tok): (_, ConsumedToken<[A; 3]>) = batch_array(ones, |i, a: &mut A| {
+        a.0 *= 2;
+        Ok::<_, ()>(i as u8 + a.0)
+    })
+    .unwrap();
+
+    assert_eq!(outputs, [0, 3, 6]);
+    assert_eq!((a0.0, a1.0, a2.0), (0, 2, 4));
+    // GOOD!
+}
@@ -0,0 +1,58 @@
+use onemut::{
+    cell::{borrow_all_mut, BorrowAllMutError},
+    from_apply::OneMuts,
+    Apply,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+struct A(pub u8);
+
+#[derive(Clone, Debug)]
+struct B(pub u8);
+
+#[test]
+fn borrow_and_apply() {
+    let a = Rc::new(RefCell::new(A(0)));
+    let b = Rc::new(RefCell::new(B(0)));
+
+    {
+        let mut guards = borrow_all_mut((&*a, &*b)).unwrap();
+        let (amut, bmut) = guards.one_muts();
+        let a = amut.unchecked_prepare(|a: &mut A| {
+            a.0 += 1;
+            Ok::<_, ()>(())
+        });
+        let b = bmut.unchecked_prepare(|b: &mut B| {
+            b.0 += 1;
+            Ok(())
+        });
+        let _ok = a.chain(b).apply().unwrap();
+    }
+
+    // all borrows were released together
+    assert_eq!(a.borrow().0, 1);
+    assert_eq!(a.borrow().0, b.borrow().0);
+    // GOOD!
+}
+
+#[test]
+fn already_borrowed() {
+    let a = Rc::new(RefCell::new(A(0)));
+    let b = Rc::new(RefCell::new(B(0)));
+
+    {
+        let _held = b.borrow();
+        let err = borrow_all_mut((&*a, &*b)).unwrap_err();
+        assert_eq!(err, BorrowAllMutError { index: 1 });
+    }
+
+    // `a` was released after the failed borrow
+    assert!(a.try_borrow_mut().is_ok());
+
+    // the same cell cannot be borrowed twice
+    let err = borrow_all_mut((&*a, &*a)).unwrap_err();
+    assert_eq!(err, BorrowAllMutError { index: 1 });
+    // GOOD!
+}
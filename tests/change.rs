@@ -0,0 +1,75 @@
+use onemut::{Apply, Change, ContentHash, OneMut};
+
+#[derive(Clone, Debug, PartialEq)]
+struct A(pub u8);
+
+#[derive(Clone, Debug, PartialEq)]
+struct B(pub u8, pub u8);
+
+/// Only the first field is hashed, so the second one collides.
+impl ContentHash for B {
+    fn content_hash(&self) -> u64 {
+        self.0 as u64
+    }
+}
+
+#[test]
+fn compared() {
+    let mut a1 = A(0);
+    let mut a2 = A(0);
+
+    let a1mut = OneMut::new(&mut a1);
+    let a2mut = OneMut::new(&mut a2);
+    let a1_prep = a1mut.unchecked_prepare_compared(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    });
+    let a2_prep = a2mut.unchecked_prepare_compared(|a: &mut A| {
+        // touched, but ends up equal
+        a.0 += 1;
+        a.0 -= 1;
+        Ok(())
+    });
+    let ((((), c1), ((), c2)), _toks) = a1_prep.chain(a2_prep).apply().unwrap();
+    assert_eq!(c1, Change::Changed);
+    assert_eq!(c2, Change::Unchanged);
+
+    assert_eq!((a1.0, a2.0), (1, 0));
+    // GOOD!
+}
+
+#[test]
+fn hashed() {
+    let mut b = B(0, 0);
+
+    let bmut = OneMut::new(&mut b);
+    let b_prep = bmut.unchecked_prepare_hashed(|b: &mut B| {
+        // touched, but ends up equal
+        b.1 += 1;
+        b.1 -= 1;
+        Ok::<_, ()>(())
+    });
+    let (((), change), _tok) = b_prep.apply().unwrap();
+    assert!(!change.is_changed());
+
+    let bmut = OneMut::new(&mut b);
+    let b_prep = bmut.unchecked_prepare_hashed(|b: &mut B| {
+        b.1 += 1;
+        Ok::<_, ()>(())
+    });
+    let (((), change), _tok) = b_prep.apply().unwrap();
+    // the hashes collided, but the copy was still different
+    assert!(change.is_changed());
+    assert_eq!(b.1, 1);
+
+    let bmut = OneMut::new(&mut b);
+    let b_prep = bmut.unchecked_prepare_hashed(|b: &mut B| {
+        b.0 += 1;
+        b.1 += 1;
+        Ok::<_, ()>(())
+    });
+    let (((), change), _tok) = b_prep.apply().unwrap();
+    assert!(change.is_changed());
+    assert_eq!((b.0, b.1), (1, 2));
+    // GOOD!
+}
@@ -0,0 +1,133 @@
+use onemut::cow::CowMut;
+use onemut::{Apply, OneMut};
+
+#[derive(Clone, Debug)]
+struct A(pub i8);
+
+#[derive(Clone, Debug)]
+struct B(pub i8);
+
+#[derive(Clone, Debug)]
+struct C(pub i8);
+
+#[derive(Debug, PartialEq)]
+struct Unbalanced;
+
+/// The ledger must balance.
+fn balanced((a, b): (&A, &B)) -> Result<(), Unbalanced> {
+    if a.0 + b.0 == 0 {
+        Ok(())
+    } else {
+        Err(Unbalanced)
+    }
+}
+
+#[test]
+fn balanced_commit() {
+    let mut a = A(0);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0 += 5;
+        Ok(())
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 -= 5;
+        Ok(())
+    });
+    let (((), ()), _toks) = a_prep.chain(b_prep).with_check(balanced).apply().unwrap();
+
+    assert_eq!((a.0, b.0), (5, -5));
+    // GOOD!
+}
+
+#[test]
+fn unbalanced_commit() {
+    let mut a = A(0);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0 += 5;
+        Ok(())
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        // mistakenly debits a different amount
+        b.0 -= 4;
+        Ok(())
+    });
+    let (err, _toks) = a_prep
+        .chain(b_prep)
+        .with_check(|(a, b): (&A, &B)| balanced((a, b)))
+        .apply()
+        .unwrap_err();
+    assert_eq!(err, Unbalanced);
+
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (0, 0));
+    // GOOD!
+}
+
+#[test]
+fn nested() {
+    let mut a = A(0);
+    let mut b = B(0);
+    let mut c = C(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let cmut = OneMut::new(&mut c);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0 += 5;
+        Ok(())
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 -= 5;
+        Ok(())
+    });
+    let c_prep = cmut.unchecked_prepare(|c: &mut C| {
+        c.0 += 1;
+        Ok(())
+    });
+    // the checked chain is a member of another chain
+    let ((((), ()), ()), _toks) = a_prep
+        .chain(b_prep)
+        .with_check(balanced)
+        .chain(c_prep)
+        .apply()
+        .unwrap();
+
+    assert_eq!((a.0, b.0, c.0), (5, -5, 1));
+    // GOOD!
+}
+
+#[test]
+fn lazy_member() {
+    let mut a = A(0);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    // the copy of a lazy member is not a `T`
+    let a_prep = amut.unchecked_prepare_lazy(|a: &mut CowMut<A>| {
+        a.0 += 5;
+        Ok(())
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 -= 4;
+        Ok(())
+    });
+    let (err, _toks) = a_prep
+        .chain(b_prep)
+        .with_check(balanced)
+        .apply()
+        .unwrap_err();
+    assert_eq!(err, Unbalanced);
+
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (0, 0));
+    // GOOD!
+}
@@ -0,0 +1,102 @@
+use onemut::{
+    cow::{ArcMut, CowMut},
+    Apply, OneMut,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+struct A(pub Vec<u8>);
+
+#[derive(Clone, Debug)]
+struct B(pub Vec<u8>);
+
+#[test]
+fn make_mut() {
+    let mut a = Arc::new(A(vec![0]));
+    let mut b = Arc::new(B(vec![0]));
+    let old_a = Arc::clone(&a);
+    let old_b = Arc::clone(&b);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare_cow(|a: &mut ArcMut<A>| {
+        a.0.push(1);
+        Ok::<_, ()>(a.0.len())
+    });
+    let b_prep = bmut.unchecked_prepare_cow(|b: &mut ArcMut<B>| {
+        // only reads
+        Ok(b.0.len())
+    });
+    let ((a_len, b_len), _toks) = a_prep.chain(b_prep).apply().unwrap();
+    assert_eq!((a_len, b_len), (2, 1));
+
+    // `a` got cloned on write
+    assert!(!Arc::ptr_eq(&a, &old_a));
+    assert_eq!(a.0, vec![0, 1]);
+    assert_eq!(old_a.0, vec![0]);
+
+    // `b` was never cloned
+    assert!(Arc::ptr_eq(&b, &old_b));
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut a = Arc::new(A(vec![0]));
+    let old_a = Arc::clone(&a);
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare_cow(|a: &mut ArcMut<A>| {
+        a.0.push(1);
+        Err::<(), _>(())
+    });
+    let (_err, _tok) = a_prep.apply().unwrap_err();
+
+    // the internal state is kept intact
+    assert!(Arc::ptr_eq(&a, &old_a));
+    assert_eq!(a.0, vec![0]);
+    // GOOD!
+}
+
+static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts it's clones.
+#[derive(Debug)]
+struct C(pub u8);
+
+impl Clone for C {
+    fn clone(&self) -> Self {
+        CLONES.fetch_add(1, Ordering::SeqCst);
+        C(self.0)
+    }
+}
+
+#[test]
+fn lazy() {
+    let mut a = C(0);
+    let mut b = C(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare_lazy(|a: &mut CowMut<C>| {
+        a.0 += 1;
+        Ok::<_, ()>(a.is_written())
+    });
+    let b_prep = bmut.unchecked_prepare_lazy(|b: &mut CowMut<C>| {
+        // the condition isn't met, so `b` is only read
+        if b.0 > 0 {
+            b.0 += 1;
+        }
+        Ok(b.is_written())
+    });
+    let ((a_written, b_written), _toks) = a_prep.chain(b_prep).apply().unwrap();
+    assert!(a_written);
+    assert!(!b_written);
+
+    // only `a` got cloned
+    assert_eq!(CLONES.load(Ordering::SeqCst), 1);
+    assert_eq!(a.0, 1);
+    assert_eq!(b.0, 0);
+    // GOOD!
+}
@@ -0,0 +1,119 @@
+use onemut::{Apply, Either, OneMut, Pending};
+
+#[derive(Clone, Debug)]
+struct A(pub Vec<u8>);
+
+#[derive(Clone, Debug)]
+struct B(pub u8);
+
+#[derive(Debug, PartialEq)]
+struct Full;
+
+#[test]
+fn fallback() {
+    let mut a = A(vec![1, 2]);
+
+    let amut = OneMut::new(&mut a);
+    let (branch, _tok) = amut
+        .unchecked_prepare(|a: &mut A| {
+            a.0.push(3);
+            if a.0.len() > 2 {
+                return Err(Full);
+            }
+            Ok(a.0.len())
+        })
+        .or_else(|_e: Full, a: &mut A| {
+            // the fresh copy doesn't have the `3`
+            a.0.clear();
+            a.0.push(3);
+            Ok::<_, ()>(())
+        })
+        .apply()
+        .unwrap();
+
+    assert_eq!(branch, Either::Right(()));
+    assert_eq!(a.0, vec![3]);
+    // GOOD!
+}
+
+#[test]
+fn both_failed() {
+    let mut a = A(vec![1, 2]);
+
+    let amut = OneMut::new(&mut a);
+    let (e, _tok) = amut
+        .unchecked_prepare(|a: &mut A| {
+            a.0.push(3);
+            Err::<(), _>(Full)
+        })
+        .or_else(|e: Full, a: &mut A| {
+            a.0.clear();
+            Err::<(), _>(e)
+        })
+        .apply()
+        .unwrap_err();
+
+    assert_eq!(e, Full);
+    assert_eq!(a.0, vec![1, 2]);
+    // GOOD!
+}
+
+#[test]
+fn chained_branches() {
+    let mut a = A(vec![]);
+    let mut b = B(0);
+
+    let append = true;
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    // the branches have different closure types
+    let a_prep = if append {
+        Either::Left(amut.unchecked_prepare(|a: &mut A| {
+            a.0.push(1);
+            Ok(a.0.len())
+        }))
+    } else {
+        Either::Right(amut.unchecked_prepare(|a: &mut A| {
+            a.0.clear();
+            Ok(())
+        }))
+    };
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 += 1;
+        Ok::<_, ()>(())
+    });
+    let ((branch, ()), _toks) = a_prep.branch().chain(b_prep).apply().unwrap();
+
+    assert_eq!(branch, Either::Left(1));
+    assert_eq!((a.0, b.0), (vec![1], 1));
+    // GOOD!
+}
+
+#[test]
+fn fallback_then_pending() {
+    let mut a = A(vec![1, 2]);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let ((branch, ()), _toks) = amut
+        .unchecked_prepare(|a: &mut A| {
+            a.0.push(3);
+            Err::<(), _>(Full)
+        })
+        .or_else(|_e: Full, a: &mut A| {
+            a.0.clear();
+            Ok::<_, ()>(())
+        })
+        .and_then_pending(bmut, |a: Pending<&A>, b: &mut B| {
+            // reads the copy of the fallback
+            b.0 = a.0.len() as u8 + 1;
+            Ok(())
+        })
+        .apply()
+        .unwrap();
+
+    assert_eq!(branch, Either::Right(()));
+    assert_eq!((a.0, b.0), (vec![], 1));
+    // GOOD!
+}
@@ -0,0 +1,112 @@
+use onemut::{Apply, History, HistoryError, OneMut};
+
+#[derive(Clone, Debug)]
+struct A(pub u8);
+
+#[test]
+fn undo_redo() {
+    let mut history = History::new(A(0));
+
+    let (ok, _tok) = history
+        .edit()
+        .unchecked_prepare(|a: &mut A| {
+            a.0 += 1;
+            Ok::<_, ()>(a.0)
+        })
+        .apply()
+        .unwrap();
+    assert_eq!(ok, 1);
+
+    // failed edits are not recorded
+    let (_err, _tok) = history
+        .edit()
+        .unchecked_prepare(|a: &mut A| {
+            a.0 += 1;
+            Err::<(), _>(())
+        })
+        .apply()
+        .unwrap_err();
+    assert_eq!(history.current().0, 1);
+    assert_eq!(history.undo_len(), 1);
+
+    let ((), _tok) = history.undo().unwrap();
+    assert_eq!(history.current().0, 0);
+    let (err, _tok) = history.undo().unwrap_err();
+    assert_eq!(err, HistoryError::NothingToUndo);
+
+    let ((), _tok) = history.redo().unwrap();
+    assert_eq!(history.current().0, 1);
+    let (err, _tok) = history.redo().unwrap_err();
+    assert_eq!(err, HistoryError::NothingToRedo);
+    // GOOD!
+}
+
+#[test]
+fn depth() {
+    let mut history = History::with_depth(A(0), 2);
+
+    for _ in 0..5 {
+        let _ok = history
+            .edit()
+            .unchecked_prepare(|a: &mut A| {
+                a.0 += 1;
+                Ok::<_, ()>(())
+            })
+            .apply()
+            .unwrap();
+    }
+    assert_eq!(history.current().0, 5);
+    assert_eq!(history.undo_len(), 2);
+
+    let _ok = history.undo().unwrap();
+    let _ok = history.undo().unwrap();
+    assert_eq!(history.current().0, 3);
+    assert!(history.undo().is_err());
+
+    // a new edit discards the redo states
+    let _ok = history
+        .edit()
+        .unchecked_prepare(|a: &mut A| {
+            a.0 = 10;
+            Ok::<_, ()>(())
+        })
+        .apply()
+        .unwrap();
+    assert_eq!(history.redo_len(), 0);
+    assert_eq!(history.into_inner().0, 10);
+    // GOOD!
+}
+
+#[test]
+fn chained() {
+    let mut history = History::new(A(0));
+    let mut b = A(0);
+
+    // a failed chain is not recorded
+    let a_prep = history.edit().unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    });
+    let b_prep = OneMut::new(&mut b).unchecked_prepare(|b: &mut A| {
+        b.0 += 1;
+        Err::<(), _>(())
+    });
+    let (_err, _toks) = a_prep.chain(b_prep).apply().unwrap_err();
+    assert_eq!((history.current().0, history.undo_len()), (0, 0));
+
+    let a_prep = history.edit().unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    });
+    let b_prep = OneMut::new(&mut b).unchecked_prepare(|b: &mut A| {
+        b.0 += 1;
+        Ok(())
+    });
+    let (((), ()), _toks) = a_prep.chain(b_prep).apply().unwrap();
+    assert_eq!((history.current().0, history.undo_len()), (1, 1));
+    assert_eq!(b.0, 1);
+
+    let ((), _tok) = history.undo().unwrap();
+    assert_eq!(history.current().0, 0);
+    // GOOD!
+}
@@ -0,0 +1,164 @@
+use onemut::retry::Backoff;
+use onemut::{Apply, Invariant, InvariantError, OneMut};
+
+/// Must be sorted.
+#[derive(Clone, Debug, PartialEq)]
+struct A(pub Vec<u8>);
+
+impl Invariant for A {
+    fn check(&self) -> Result<(), InvariantError> {
+        if self.0.windows(2).all(|w| w[0] <= w[1]) {
+            Ok(())
+        } else {
+            Err(InvariantError::new("A must be sorted"))
+        }
+    }
+}
+
+/// Must be below 10.
+#[derive(Clone, Debug, PartialEq)]
+struct B(pub u8);
+
+impl Invariant for B {
+    fn check(&self) -> Result<(), InvariantError> {
+        if self.0 < 10 {
+            Ok(())
+        } else {
+            Err(InvariantError::new("B must be below 10"))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Error {
+    Invariant(InvariantError),
+}
+
+impl From<InvariantError> for Error {
+    fn from(e: InvariantError) -> Self {
+        Error::Invariant(e)
+    }
+}
+
+#[test]
+fn upheld() {
+    let mut a = A(vec![1, 3]);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare_checked(|a: &mut A| {
+        a.0.insert(1, 2);
+        Ok::<_, Error>(())
+    });
+    let b_prep = bmut.unchecked_prepare_checked(|b: &mut B| {
+        b.0 += 2;
+        Ok(())
+    });
+    let (((), ()), _toks) = a_prep.chain(b_prep).apply().unwrap();
+
+    assert_eq!((a.0, b.0), (vec![1, 2, 3], 2));
+    // GOOD!
+}
+
+#[test]
+fn violated() {
+    let mut a = A(vec![1, 3]);
+    let mut b = B(9);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare_checked(|a: &mut A| {
+        a.0.push(4);
+        Ok::<_, Error>(())
+    });
+    let b_prep = bmut.unchecked_prepare_checked(|b: &mut B| {
+        // mistakenly breaks `B`'s invariant
+        b.0 += 1;
+        Ok(())
+    });
+    let (err, _toks) = a_prep.chain(b_prep).apply().unwrap_err();
+    assert_eq!(err, Error::Invariant(InvariantError::new("B must be below 10")));
+
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (vec![1, 3], 9));
+    // GOOD!
+}
+
+#[test]
+fn registered() {
+    let mut a = A(vec![1, 3]);
+    let mut b = B(9);
+
+    let amut = OneMut::new(&mut a).with_invariant();
+    let bmut = OneMut::new(&mut b).with_invariant();
+    // a plain `prepare` is still checked
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0.push(4);
+        Ok::<_, Error>(())
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 += 1;
+        Ok(())
+    });
+    let (err, _toks) = a_prep.chain(b_prep).apply().unwrap_err();
+    assert_eq!(
+        err,
+        Error::Invariant(InvariantError::new("B must be below 10"))
+    );
+
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (vec![1, 3], 9));
+    // GOOD!
+}
+
+#[test]
+fn registered_and_then() {
+    let mut a = A(vec![1, 3]);
+    let mut b = B(9);
+
+    let amut = OneMut::new(&mut a).with_invariant();
+    let bmut = OneMut::new(&mut b);
+    let (err, _toks) = amut
+        .unchecked_prepare(|a: &mut A| {
+            // mistakenly breaks `A`'s invariant
+            a.0.push(0);
+            Ok::<_, Error>(a.0.len())
+        })
+        .and_then(bmut, |len: &usize, b: &mut B| {
+            b.0 = *len as u8;
+            Ok(())
+        })
+        .apply()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        Error::Invariant(InvariantError::new("A must be sorted"))
+    );
+
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (vec![1, 3], 9));
+    // GOOD!
+}
+
+#[test]
+fn registered_retried() {
+    let mut a = A(vec![1, 3]);
+
+    let amut = OneMut::new(&mut a).with_invariant();
+    let (err, _tok) = amut
+        .unchecked_prepare(|a: &mut A| {
+            // the retried attempts are also checked
+            a.0.push(0);
+            Ok::<_, Error>(())
+        })
+        .retry(Backoff::new(2))
+        .apply()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        Error::Invariant(InvariantError::new("A must be sorted"))
+    );
+    assert_eq!(a.0, vec![1, 3]);
+    // GOOD!
+}
@@ -0,0 +1,61 @@
+use onemut::joint::unchecked_prepare_joint;
+use onemut::{Apply, OneMut};
+
+#[derive(Clone, Debug)]
+struct A(pub Vec<u8>);
+
+#[derive(Clone, Debug)]
+struct B(pub Vec<u8>);
+
+#[derive(Clone, Debug)]
+struct C(pub u8);
+
+#[derive(Debug, PartialEq)]
+struct Missing;
+
+#[test]
+fn moved() {
+    let mut a = A(vec![1, 2]);
+    let mut b = B(vec![]);
+    let mut c = C(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let cmut = OneMut::new(&mut c);
+    let ab_prep = unchecked_prepare_joint((amut, bmut), |(a, b): (&mut A, &mut B)| {
+        // moves an item from the inventory into the cart
+        let item = a.0.pop().ok_or(Missing)?;
+        b.0.push(item);
+        Ok::<_, Missing>(item)
+    });
+    let c_prep = cmut.unchecked_prepare(|c: &mut C| {
+        c.0 += 1;
+        Ok(c.0)
+    });
+    let ((item, count), _toks) = ab_prep.chain(c_prep).apply().unwrap();
+    assert_eq!((item, count), (2, 1));
+
+    assert_eq!((a.0, b.0, c.0), (vec![1], vec![2], 1));
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut a = A(vec![]);
+    let mut b = B(vec![0]);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let ab_prep = unchecked_prepare_joint((amut, bmut), |(a, b): (&mut A, &mut B)| {
+        b.0.clear();
+        let item = a.0.pop().ok_or(Missing)?;
+        b.0.push(item);
+        Ok::<_, Missing>(())
+    });
+    let (err, _toks) = ab_prep.apply().unwrap_err();
+    assert_eq!(err, Missing);
+
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (vec![], vec![0]));
+    // GOOD!
+}
@@ -0,0 +1,148 @@
+use onemut::{
+    from_apply::{FromApply2, OneMuts},
+    lock::{lock_all, try_lock_all, LockError},
+    OneMut,
+};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+struct A(pub u32);
+
+#[derive(Clone, Debug)]
+struct B(pub u32);
+
+struct Inc;
+impl FromApply2<A, B> for Inc {
+    type Input = ();
+    type Return = Result<(), ()>;
+
+    fn from_apply<'tokens, 't1, 't2>(
+        (a, b): (OneMut<'t1, A>, OneMut<'t2, B>),
+        _input: Self::Input,
+    ) -> onemut::AllOrNone<'tokens, (), (), (A, B)> {
+        use onemut::Apply;
+        let a = a.unchecked_prepare(|a: &mut A| {
+            a.0 += 1;
+            Ok(())
+        });
+        let b = b.unchecked_prepare(|b: &mut B| {
+            b.0 += 1;
+            Ok(())
+        });
+        let (_, toks) = a.chain(b).apply()?;
+        Ok(((), toks))
+    }
+}
+
+#[test]
+fn opposite_orders() {
+    let a = Arc::new(Mutex::new(A(0)));
+    let b = Arc::new(RwLock::new(B(0)));
+
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let a = Arc::clone(&a);
+            let b = Arc::clone(&b);
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    // the locks are requested in opposite orders,
+                    // but they are acquired in the same order
+                    if i % 2 == 0 {
+                        let mut guards = lock_all((&*a, &*b)).unwrap();
+                        let _ok = Inc::from_apply(guards.one_muts(), ()).unwrap();
+                    } else {
+                        let mut guards = lock_all((&*b, &*a)).unwrap();
+                        let (bmut, amut) = guards.one_muts();
+                        let _ok = Inc::from_apply((amut, bmut), ()).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let a = a.lock().unwrap();
+    let b = b.read().unwrap();
+    assert_eq!(a.0, 400);
+    assert_eq!(a.0, b.0);
+    // GOOD!
+}
+
+#[test]
+fn timeout() {
+    let a = Mutex::new(A(0));
+    let b = Mutex::new(B(0));
+
+    let _held = b.lock().unwrap();
+    let err = try_lock_all((&a, &b), Duration::from_millis(10)).unwrap_err();
+    assert_eq!(err, LockError::Timeout);
+
+    // `a` was released after the failed acquisition
+    assert!(a.try_lock().is_ok());
+    // GOOD!
+}
+
+#[test]
+fn repeated() {
+    let a = Mutex::new(A(0));
+    let b = Mutex::new(B(0));
+
+    let err = lock_all((&a, &b, &a)).map(|_| ()).unwrap_err();
+    assert_eq!(err, LockError::Repeated { index: 2 });
+
+    // no lock was acquired
+    assert!(a.try_lock().is_ok());
+    assert!(b.try_lock().is_ok());
+    // GOOD!
+}
+
+#[test]
+fn handles() {
+    let a = Arc::new(Mutex::new(A(0)));
+    let b = Arc::new(Mutex::new(B(0)));
+
+    // clones of the same `Arc` are the same lock
+    let a2 = Arc::clone(&a);
+    let err = lock_all((&a, &a2)).map(|_| ()).unwrap_err();
+    assert_eq!(err, LockError::Repeated { index: 1 });
+
+    // the handles are ordered by the locks they point into
+    let b2 = Arc::clone(&b);
+    let mut guards = lock_all((&b2, &a2)).unwrap();
+    let (bmut, amut) = guards.one_muts();
+    let _ok = Inc::from_apply((amut, bmut), ()).unwrap();
+    drop(guards);
+
+    assert_eq!((a.lock().unwrap().0, b.lock().unwrap().0), (1, 1));
+    // GOOD!
+}
+
+#[test]
+fn poisoned() {
+    let a = Arc::new(Mutex::new(A(0)));
+    let b = Mutex::new(B(0));
+
+    let a2 = Arc::clone(&a);
+    let _panicked = std::thread::spawn(move || {
+        let _guard = a2.lock().unwrap();
+        panic!("poisons the lock");
+    })
+    .join()
+    .unwrap_err();
+
+    let err = lock_all((&*a, &b)).unwrap_err();
+    assert_eq!(err, LockError::Poisoned);
+    let err = try_lock_all((&*a, &b), Duration::from_millis(10)).unwrap_err();
+    assert_eq!(err, LockError::Poisoned);
+
+    // `b` was released after the failed acquisitions
+    assert!(b.try_lock().is_ok());
+
+    // recovery must be explicit
+    a.clear_poison();
+    let _guards = lock_all((&*a, &b)).unwrap();
+    // GOOD!
+}
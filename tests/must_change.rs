@@ -0,0 +1,81 @@
+use onemut::{Apply, OneMut, Unchanged};
+
+#[derive(Clone, Debug, PartialEq)]
+struct A(pub u8);
+
+#[derive(Clone, Debug, PartialEq)]
+struct B(pub u8);
+
+#[derive(Debug, PartialEq)]
+enum Error {
+    Unchanged,
+}
+
+impl From<Unchanged> for Error {
+    fn from(_: Unchanged) -> Self {
+        Error::Unchanged
+    }
+}
+
+#[test]
+fn example_4() {
+    let mut a = A(0);
+    let mut b = B(0);
+
+    let err = example_4_(&mut a, &mut b, false).unwrap_err();
+
+    assert_eq!(err, Error::Unchanged);
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (0, 0));
+    // GOOD!
+}
+
+fn example_4_(a: &mut A, b: &mut B, cond: bool) -> Result<u8, Error> {
+    let amut = OneMut::new(a);
+    let bmut = OneMut::new(b);
+    let a_prep = amut.unchecked_prepare_must_change(|a: &mut A| {
+        if cond {
+            a.0 += 1;
+        };
+        // mistakenly forgets to mutate `a` on !cond
+        Ok(a.0)
+    });
+    let b_prep = bmut.unchecked_prepare_must_change(|b: &mut B| {
+        b.0 += 1;
+        Ok(b.0)
+    });
+    match a_prep.chain(b_prep).apply() {
+        Ok(((a, b), _toks)) => Ok(a + b),
+        Err((e, _toks)) => Err(e),
+    }
+}
+
+#[test]
+fn predicate() {
+    let mut a = A(0);
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare_must_change_by(
+        |a: &mut A| {
+            a.0 += 1;
+            Ok::<_, Error>(())
+        },
+        // must increase by at least 2
+        |old: &A, new: &A| new.0 >= old.0 + 2,
+    );
+    let (err, _tok) = a_prep.apply().unwrap_err();
+    assert_eq!(err, Error::Unchanged);
+    assert_eq!(a.0, 0);
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare_must_change_by(
+        |a: &mut A| {
+            a.0 += 2;
+            Ok::<_, Error>(())
+        },
+        |old: &A, new: &A| new.0 >= old.0 + 2,
+    );
+    let ((), _tok) = a_prep.apply().unwrap();
+    assert_eq!(a.0, 2);
+    // GOOD!
+}
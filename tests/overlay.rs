@@ -0,0 +1,106 @@
+use onemut::{Apply, OneMut, Overlaid};
+use std::cell::Cell;
+
+thread_local! {
+    // each test runs on it's own thread
+    static CLONES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts it's clones, per thread.
+#[derive(Debug, PartialEq)]
+pub struct C(pub u8);
+
+impl Clone for C {
+    fn clone(&self) -> Self {
+        CLONES.with(|c| c.set(c.get() + 1));
+        C(self.0)
+    }
+}
+
+#[derive(Clone, Debug, Overlaid)]
+pub struct A {
+    pub x: C,
+    pub y: C,
+    pub z: C,
+}
+
+/// `onemut`, under another path.
+mod renamed {
+    pub use onemut::*;
+}
+
+#[derive(Clone, Debug, Overlaid)]
+#[onemut(crate = crate::renamed)]
+pub struct D {
+    pub x: C,
+}
+
+onemut::overlay! {
+    #[derive(Clone, Debug)]
+    struct B {
+        x: C,
+    }
+}
+
+#[test]
+fn dirty_fields() {
+    let mut a = A {
+        x: C(0),
+        y: C(0),
+        z: C(0),
+    };
+    let mut b = B { x: C(0) };
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare_overlay(|a: &mut AOverlay| {
+        a.x_mut().0 += 1;
+        a.y_mut().0 = a.x().0 + a.z().0 + 1;
+        Ok::<_, ()>(a.is_written())
+    });
+    let b_prep = bmut.unchecked_prepare_overlay(|b: &mut BOverlay| {
+        // only reads
+        Ok(b.is_written() || b.x().0 > 0)
+    });
+    let ((a_written, b_written), _toks) = a_prep.chain(b_prep).apply().unwrap();
+    assert!(a_written);
+    assert!(!b_written);
+
+    // only `a.x` and `a.y` got cloned
+    assert_eq!(CLONES.with(Cell::get), 2);
+    assert_eq!((a.x.0, a.y.0, a.z.0), (1, 2, 0));
+    assert_eq!(b.x.0, 0);
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut b = B { x: C(0) };
+
+    let bmut = OneMut::new(&mut b);
+    let b_prep = bmut.unchecked_prepare_overlay(|b: &mut BOverlay| {
+        b.x_mut().0 += 1;
+        Err::<(), _>(())
+    });
+    let (_err, _tok) = b_prep.apply().unwrap_err();
+
+    // the internal state is kept intact
+    assert_eq!(CLONES.with(Cell::get), 1);
+    assert_eq!(b.x.0, 0);
+    // GOOD!
+}
+
+#[test]
+fn crate_path() {
+    let mut d = D { x: C(0) };
+
+    let dmut = OneMut::new(&mut d);
+    let d_prep = dmut.unchecked_prepare_overlay(|d: &mut DOverlay| {
+        d.x_mut().0 += 1;
+        Ok::<_, ()>(())
+    });
+    let ((), _tok) = d_prep.apply().unwrap();
+
+    assert_eq!(d.x.0, 1);
+    // GOOD!
+}
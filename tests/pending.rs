@@ -0,0 +1,163 @@
+use onemut::cow::{ArcMut, CowMut};
+use onemut::stm::{unchecked_prepare_atomically, TVar};
+use onemut::{Apply, OneMut, Pending};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+struct A(pub Vec<u8>);
+
+/// A cache derived from `A`.
+#[derive(Clone, Debug)]
+struct B(pub u32);
+
+#[derive(Clone, Debug)]
+struct C(pub String);
+
+#[test]
+fn derived() {
+    let mut a = A(vec![1, 2]);
+    let mut b = B(3);
+    let mut c = C(String::new());
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let cmut = OneMut::new(&mut c);
+    let ((((), ()), ()), _toks) = amut
+        .unchecked_prepare(|a: &mut A| {
+            a.0.push(3);
+            Ok::<_, ()>(())
+        })
+        .and_then_pending(bmut, |a: Pending<&A>, b: &mut B| {
+            // reads the pending `A`, not the original one
+            b.0 = a.0.iter().map(|x| *x as u32).sum();
+            Ok(())
+        })
+        .and_then_pending(cmut, |ab: Pending<(&A, &B)>, c: &mut C| {
+            let (a, b) = ab.get();
+            *c = C(format!("{}:{}", a.0.len(), b.0));
+            Ok(())
+        })
+        .apply()
+        .unwrap();
+
+    assert_eq!((a.0, b.0, c.0), (vec![1, 2, 3], 6, "3:6".to_string()));
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut a = A(vec![1, 2]);
+    let mut b = B(3);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let (_err, _toks) = amut
+        .unchecked_prepare(|a: &mut A| {
+            a.0.clear();
+            Ok(())
+        })
+        .and_then_pending(bmut, |a: Pending<&A>, b: &mut B| {
+            if a.0.is_empty() {
+                return Err(());
+            }
+            b.0 = a.0.iter().map(|x| *x as u32).sum();
+            Ok(())
+        })
+        .apply()
+        .unwrap_err();
+
+    // the internal state is kept intact
+    assert_eq!((a.0, b.0), (vec![1, 2], 3));
+    // GOOD!
+}
+
+#[test]
+fn lazy() {
+    let mut a1 = A(vec![1, 2]);
+    let mut a2 = A(vec![1, 2]);
+    let mut b1 = B(0);
+    let mut b2 = B(0);
+
+    // not mutably accessed, so there is no copy
+    let a1mut = OneMut::new(&mut a1);
+    let b1mut = OneMut::new(&mut b1);
+    let (((), ()), _toks) = a1mut
+        .unchecked_prepare_lazy(|a: &mut CowMut<A>| {
+            let _len = a.0.len();
+            Ok::<_, ()>(())
+        })
+        .and_then_pending(b1mut, |a: Pending<&A>, b: &mut B| {
+            // reads the original `A`
+            b.0 = a.0.iter().map(|x| *x as u32).sum();
+            Ok(())
+        })
+        .apply()
+        .unwrap();
+
+    // mutably accessed, so there is a copy
+    let a2mut = OneMut::new(&mut a2);
+    let b2mut = OneMut::new(&mut b2);
+    let (((), ()), _toks) = a2mut
+        .unchecked_prepare_lazy(|a: &mut CowMut<A>| {
+            a.0.push(3);
+            Ok::<_, ()>(())
+        })
+        .and_then_pending(b2mut, |a: Pending<&A>, b: &mut B| {
+            // reads the pending `A`
+            b.0 = a.0.iter().map(|x| *x as u32).sum();
+            Ok(())
+        })
+        .apply()
+        .unwrap();
+
+    assert_eq!((a1.0, b1.0), (vec![1, 2], 3));
+    assert_eq!((a2.0, b2.0), (vec![1, 2, 3], 6));
+    // GOOD!
+}
+
+#[test]
+fn cow() {
+    let mut a = Arc::new(A(vec![1, 2]));
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let (((), ()), _toks) = amut
+        .unchecked_prepare_cow(|a: &mut ArcMut<A>| {
+            a.0.push(3);
+            Ok::<_, ()>(())
+        })
+        .and_then_pending(bmut, |a: Pending<&A>, b: &mut B| {
+            // reads through the pending `Arc`
+            b.0 = a.0.iter().map(|x| *x as u32).sum();
+            Ok(())
+        })
+        .apply()
+        .unwrap();
+
+    assert_eq!((a.0.clone(), b.0), (vec![1, 2, 3], 6));
+    // GOOD!
+}
+
+#[test]
+fn atomically() {
+    let a = TVar::new(A(vec![1, 2]));
+    let mut b = B(0);
+
+    let bmut = OneMut::new(&mut b);
+    let (((), ()), _toks) = unchecked_prepare_atomically((&a,), |_tx, (a,)| {
+        a.0.push(3);
+        Ok::<_, ()>(())
+    })
+    .and_then_pending(bmut, |a: Pending<&(A,)>, b: &mut B| {
+        // reads the pending values of the `TVar`s
+        let (a,) = a.get();
+        b.0 = a.0.iter().map(|x| *x as u32).sum();
+        Ok(())
+    })
+    .apply()
+    .unwrap();
+
+    assert_eq!((a.get().0, b.0), (vec![1, 2, 3], 6));
+    // GOOD!
+}
@@ -0,0 +1,149 @@
+#![cfg(feature = "persistent")]
+
+use onemut::{Apply, OneMut, PMap, PVector};
+
+#[test]
+fn vector() {
+    let mut a: PVector<u32> = (0..2000).collect();
+    let old_a = a.clone();
+    assert!(a.ptr_eq(&old_a));
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut PVector<u32>| {
+        a.push(2000);
+        a.push(2001);
+        a.set(0, 100);
+        a.set(1, 101);
+        let last = a.pop();
+        Ok::<_, ()>(last)
+    });
+    let (last, _tok) = a_prep.apply().unwrap();
+    assert_eq!(last, Some(2001));
+
+    assert_eq!(a.len(), 2001);
+    assert_eq!(a.get(0), Some(&100));
+    assert_eq!(a.get(1), Some(&101));
+    assert_eq!(a.get(1000), Some(&1000));
+    assert_eq!(a.get(2000), Some(&2000));
+    assert_eq!(a.get(2001), None);
+
+    // the old root is kept intact
+    assert!(!a.ptr_eq(&old_a));
+    assert!(old_a.iter().copied().eq(0..2000));
+    // GOOD!
+}
+
+#[test]
+fn vector_insert_remove() {
+    let mut a: PVector<u32> = (0..100).collect();
+    let old_a = a.clone();
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut PVector<u32>| {
+        a.insert(0, 1000);
+        a.insert(50, 1050);
+        a.insert(a.len(), 1100);
+        let removed = a.remove(1);
+        Ok::<_, ()>(removed)
+    });
+    let (removed, _tok) = a_prep.apply().unwrap();
+    assert_eq!(removed, 0);
+
+    let mut expected: Vec<u32> = (0..100).collect();
+    expected.insert(0, 1000);
+    expected.insert(50, 1050);
+    expected.push(1100);
+    expected.remove(1);
+    assert!(a.iter().copied().eq(expected));
+
+    // the old root is kept intact
+    assert!(old_a.iter().copied().eq(0..100));
+    // GOOD!
+}
+
+#[test]
+fn map() {
+    let mut a: PMap<u32, u32> = (0..5000).map(|k| (k, k)).collect();
+    let old_a = a.clone();
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut PMap<u32, u32>| {
+        for k in 0..2500 {
+            assert_eq!(a.remove(&k), Some(k));
+        }
+        *a.get_mut(&4999).unwrap() = 0;
+        assert_eq!(a.insert(5000, 5000), None);
+        assert_eq!(a.insert(2500, 0), Some(2500));
+        Ok::<_, ()>(a.len())
+    });
+    let (len, _tok) = a_prep.apply().unwrap();
+    assert_eq!(len, 2501);
+
+    assert_eq!(a.iter().count(), 2501);
+    assert_eq!(a.get(&0), None);
+    assert_eq!(a.get(&2500), Some(&0));
+    assert_eq!(a.get(&4999), Some(&0));
+    assert_eq!(a.get(&5000), Some(&5000));
+
+    // the old root is kept intact
+    assert_eq!(old_a.len(), 5000);
+    assert!((0..5000).all(|k| old_a.get(&k) == Some(&k)));
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut a: PMap<u8, u8> = (0..10).map(|k| (k, k)).collect();
+    let old_a = a.clone();
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut PMap<u8, u8>| {
+        let _old = a.remove(&0);
+        Err::<(), _>(())
+    });
+    let (_err, _tok) = a_prep.apply().unwrap_err();
+
+    // the internal state is kept intact
+    assert!(a.ptr_eq(&old_a));
+    assert_eq!(a, old_a);
+    // GOOD!
+}
+
+/// Hashes everything into the same value.
+#[derive(Clone, Default)]
+struct Colliding;
+
+impl std::hash::BuildHasher for Colliding {
+    type Hasher = Constant;
+    fn build_hasher(&self) -> Constant {
+        Constant
+    }
+}
+
+struct Constant;
+
+impl std::hash::Hasher for Constant {
+    fn finish(&self) -> u64 {
+        0
+    }
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+#[test]
+fn collisions() {
+    let mut a: PMap<u8, u8, Colliding> = (0..10).map(|k| (k, k)).collect();
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut PMap<u8, u8, Colliding>| {
+        assert_eq!(a.remove(&3), Some(3));
+        assert_eq!(a.insert(4, 0), Some(4));
+        Ok::<_, ()>(())
+    });
+    let ((), _tok) = a_prep.apply().unwrap();
+
+    assert_eq!(a.len(), 9);
+    assert_eq!(a.get(&3), None);
+    assert_eq!(a.get(&4), Some(&0));
+    assert_eq!(a.get(&9), Some(&9));
+    // GOOD!
+}
@@ -0,0 +1,89 @@
+use onemut::{Apply, Backoff, OneMut, Retry};
+use std::cell::Cell;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+struct A(pub Vec<u8>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Error {
+    Busy,
+    Invalid,
+}
+
+#[test]
+fn transient() {
+    let mut a = A(vec![]);
+    let busy = Cell::new(2);
+
+    let amut = OneMut::new(&mut a);
+    let policy = Backoff::new(3)
+        .with_delay(Duration::from_millis(1))
+        .exponential(2)
+        .when(|e: &Error| *e == Error::Busy);
+    let (len, _tok) = amut
+        .unchecked_prepare(|a: &mut A| {
+            a.0.push(1);
+            if busy.get() > 0 {
+                busy.set(busy.get() - 1);
+                return Err(Error::Busy);
+            }
+            Ok(a.0.len())
+        })
+        .retry(policy)
+        .apply()
+        .unwrap();
+
+    // each attempt started from a fresh copy
+    assert_eq!(len, 1);
+    assert_eq!(a.0, vec![1]);
+    // GOOD!
+}
+
+#[test]
+fn give_up() {
+    let mut a = A(vec![]);
+    let attempts = Cell::new(0);
+
+    let amut = OneMut::new(&mut a);
+    let (e, _tok) = amut
+        .unchecked_prepare(|a: &mut A| {
+            attempts.set(attempts.get() + 1);
+            a.0.push(1);
+            Err::<(), _>(Error::Busy)
+        })
+        .retry(Backoff::new(2))
+        .apply()
+        .unwrap_err();
+
+    assert_eq!(e, Error::Busy);
+    assert_eq!(attempts.get(), 3);
+    assert!(a.0.is_empty());
+    // GOOD!
+}
+
+#[test]
+fn not_transient() {
+    let mut a = A(vec![]);
+    let attempts = Cell::new(0);
+
+    let amut = OneMut::new(&mut a);
+    let (e, _tok) = amut
+        .unchecked_prepare(|_a: &mut A| {
+            attempts.set(attempts.get() + 1);
+            Err::<(), _>(Error::Invalid)
+        })
+        .retry(|_attempt: usize, e: &Error| {
+            if *e == Error::Busy {
+                Retry::Now
+            } else {
+                Retry::GiveUp
+            }
+        })
+        .apply()
+        .unwrap_err();
+
+    assert_eq!(e, Error::Invalid);
+    assert_eq!(attempts.get(), 1);
+    // GOOD!
+}
@@ -0,0 +1,89 @@
+use onemut::{nested, Apply, OneMut, Savepoint};
+
+#[derive(Clone, Debug)]
+struct A(pub Vec<u8>);
+
+#[derive(Clone, Debug)]
+struct B(pub u8);
+
+#[test]
+fn nested_steps() {
+    let mut a = A(vec![]);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0.push(1);
+        // an optional step that fails, and is rolled back
+        let _res = nested(a, |a: &mut A| {
+            a.0.push(2);
+            Err::<(), _>(())
+        });
+        // an optional step that succeeds
+        let (_ok, _tok) = nested(a, |a: &mut A| {
+            a.0.push(3);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        Ok::<_, ()>(())
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 += 1;
+        Ok(())
+    });
+    let (((), ()), _toks) = a_prep.chain(b_prep).apply().unwrap();
+
+    assert_eq!((a.0, b.0), (vec![1, 3], 1));
+    // GOOD!
+}
+
+#[test]
+fn savepoints() {
+    let mut a = A(vec![]);
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0.push(1);
+
+        let mut sp = Savepoint::new(&mut *a);
+        sp.0.push(2);
+        sp.rollback();
+
+        let mut sp = Savepoint::new(&mut *a);
+        sp.0.push(3);
+        sp.release();
+
+        {
+            // dropped without a release
+            let mut sp = Savepoint::new(&mut *a);
+            sp.0.push(4);
+        }
+        Ok::<_, ()>(())
+    });
+    let ((), _tok) = a_prep.apply().unwrap();
+
+    assert_eq!(a.0, vec![1, 3]);
+    // GOOD!
+}
+
+#[test]
+fn outer_failed() {
+    let mut a = A(vec![]);
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        let (_ok, _tok) = nested(a, |a: &mut A| {
+            a.0.push(1);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        // the outer transaction still fails
+        Err::<(), _>(())
+    });
+    let (_err, _tok) = a_prep.apply().unwrap_err();
+
+    // the internal state is kept intact
+    assert!(a.0.is_empty());
+    // GOOD!
+}
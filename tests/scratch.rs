@@ -0,0 +1,79 @@
+use onemut::{ApplyScratch, OneMut, Scratch};
+
+#[test]
+fn reused() {
+    let mut a = vec![0u8; 64];
+    let mut scratch = Scratch::new();
+
+    let mut ptrs = vec![a.as_ptr()];
+    for round in 1..=6u8 {
+        let amut = OneMut::new(&mut a);
+        let a_prep = amut.unchecked_prepare(|a: &mut Vec<u8>| {
+            a[0] = round;
+            Ok::<_, ()>(())
+        });
+        let ((), _tok) = a_prep.apply_with(&mut scratch).unwrap();
+        assert_eq!(a[0], round);
+        if !ptrs.contains(&a.as_ptr()) {
+            ptrs.push(a.as_ptr());
+        }
+    }
+
+    // only the first round allocated, and then both buffers were
+    // alternating between `a` and the scratch
+    assert_eq!(ptrs.len(), 2);
+    let old = scratch.into_inner().unwrap();
+    assert_eq!(old[0], 5);
+    assert!(ptrs.contains(&old.as_ptr()));
+    // GOOD!
+}
+
+#[test]
+fn chained() {
+    let mut a = vec![0u8];
+    let mut b = String::from("b");
+    let mut scratch = Scratch::new();
+
+    for _ in 0..3 {
+        let amut = OneMut::new(&mut a);
+        let bmut = OneMut::new(&mut b);
+        let a_prep = amut.unchecked_prepare(|a: &mut Vec<u8>| {
+            a.push(1);
+            Ok::<_, ()>(a.len())
+        });
+        let b_prep = bmut.unchecked_prepare(|b: &mut String| {
+            b.push('b');
+            Ok(b.len())
+        });
+        let ((_a_len, _b_len), _toks) = a_prep.chain(b_prep).apply_with(&mut scratch).unwrap();
+    }
+
+    assert_eq!((a, b), (vec![0, 1, 1, 1], "bbbb".to_string()));
+    // the scratch holds the old values
+    assert_eq!(
+        scratch.into_inner(),
+        Some((vec![0, 1, 1], "bbb".to_string()))
+    );
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut a = vec![0u8];
+    let mut scratch = Scratch::with(Vec::with_capacity(8));
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut Vec<u8>| {
+        a.push(1);
+        Err::<(), _>(())
+    });
+    let (_err, _tok) = a_prep.apply_with(&mut scratch).unwrap_err();
+
+    // the internal state is kept intact
+    assert_eq!(a, vec![0]);
+    // and the discarded copy is kept for reuse
+    let discarded = scratch.into_inner().unwrap();
+    assert_eq!(discarded, vec![0, 1]);
+    assert!(discarded.capacity() >= 8);
+    // GOOD!
+}
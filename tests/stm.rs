@@ -0,0 +1,201 @@
+use onemut::stm::{atomically, unchecked_prepare_atomically, TVar};
+use onemut::{Apply, OneMut};
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+struct Insufficient;
+
+#[test]
+fn transfers() {
+    let accounts: Arc<[TVar<i64>; 3]> = Arc::new([TVar::new(100), TVar::new(100), TVar::new(100)]);
+
+    let writers: Vec<_> = (0..3)
+        .map(|i| {
+            let accounts = Arc::clone(&accounts);
+            std::thread::spawn(move || {
+                for n in 0..200 {
+                    let from = &accounts[i];
+                    let to = &accounts[(i + 1 + n % 2) % 3];
+                    let amount = (n % 7) as i64;
+                    let _res = atomically((from, to), |_tx, (from, to)| {
+                        if *from < amount {
+                            return Err(Insufficient);
+                        }
+                        *from -= amount;
+                        *to += amount;
+                        Ok(())
+                    });
+                }
+            })
+        })
+        .collect();
+    let reader = {
+        let accounts = Arc::clone(&accounts);
+        std::thread::spawn(move || {
+            for _ in 0..200 {
+                // read-only transaction, which only observes coherent states
+                let (total, _toks) = atomically((), |tx, ()| {
+                    let total: i64 = accounts.iter().map(|a| tx.read(a)).sum();
+                    Ok::<_, ()>(total)
+                })
+                .unwrap();
+                assert_eq!(total, 300);
+            }
+        })
+    };
+    for t in writers {
+        t.join().unwrap();
+    }
+    reader.join().unwrap();
+
+    let total: i64 = accounts.iter().map(|a| a.get()).sum();
+    assert_eq!(total, 300);
+    // GOOD!
+}
+
+#[test]
+fn reads_are_validated() {
+    let rate = Arc::new(TVar::new(2u32));
+    let value = Arc::new(TVar::new(0u32));
+
+    let writer = {
+        let rate = Arc::clone(&rate);
+        std::thread::spawn(move || {
+            for _ in 0..100 {
+                let _ok = atomically((&*rate,), |_tx, (rate,)| {
+                    *rate += 1;
+                    Ok::<_, ()>(())
+                })
+                .unwrap();
+            }
+        })
+    };
+    for _ in 0..100 {
+        // the committed value always matches the rate that was read
+        let (written, _toks) = atomically((&*value,), |tx, (value,)| {
+            let rate = tx.read(&*rate);
+            *value = rate * 10;
+            Ok::<_, ()>(rate)
+        })
+        .unwrap();
+        assert_eq!(written * 10, value.get());
+    }
+    writer.join().unwrap();
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let a = TVar::new(1u8);
+    let b = TVar::new(1u8);
+
+    let (err, _toks) = atomically((&a, &b), |_tx, (a, b)| {
+        *a -= 1;
+        if *b < 2 {
+            return Err(Insufficient);
+        }
+        *b -= 2;
+        Ok(())
+    })
+    .unwrap_err();
+    assert_eq!(err, Insufficient);
+
+    // the internal state is kept intact
+    assert_eq!((a.get(), b.get()), (1, 1));
+    assert_eq!((a.version(), b.version()), (0, 0));
+    // GOOD!
+}
+
+#[test]
+fn chained() {
+    let a = TVar::new(10u8);
+    let mut b = 0u8;
+
+    let a_prep = unchecked_prepare_atomically((&a,), |_tx, (a,)| {
+        *a -= 3;
+        Ok::<_, Insufficient>(*a)
+    });
+    let bmut = OneMut::new(&mut b);
+    let b_prep = bmut.unchecked_prepare(|b: &mut u8| {
+        *b += 3;
+        Ok(*b)
+    });
+    let ((a0, b0), _toks) = a_prep.chain(b_prep).apply().unwrap();
+
+    assert_eq!((a0, b0), (7, 3));
+    assert_eq!((a.get(), a.version()), (7, 1));
+    assert_eq!(b, 3);
+    // GOOD!
+}
+
+#[test]
+#[should_panic]
+fn repeated() {
+    let a = TVar::new(1u8);
+
+    // would otherwise always conflict with itself
+    let _res = atomically((&a, &a), |_tx, (a1, a2)| {
+        *a1 += 1;
+        *a2 += 1;
+        Ok::<_, ()>(())
+    });
+}
+
+#[test]
+#[should_panic(expected = "held by an earlier member")]
+fn chained_read_overlap() {
+    let a = TVar::new(10u8);
+    let b = TVar::new(0u8);
+
+    let a_prep = unchecked_prepare_atomically((&a,), |_tx, (a,)| {
+        *a -= 3;
+        Ok::<_, ()>(())
+    });
+    let b_prep = unchecked_prepare_atomically((&b,), |tx, (b,)| {
+        // `a` is held by the first member, so this would block forever
+        *b = tx.read(&a);
+        Ok(())
+    });
+    let _res = a_prep.chain(b_prep).apply();
+}
+
+#[test]
+#[should_panic(expected = "held by an earlier member")]
+fn chained_write_overlap() {
+    let a = TVar::new(10u8);
+
+    let a1 = unchecked_prepare_atomically((&a,), |_tx, (a,)| {
+        *a -= 3;
+        Ok::<_, ()>(())
+    });
+    let a2 = unchecked_prepare_atomically((&a,), |_tx, (a,)| {
+        *a -= 3;
+        Ok(())
+    });
+    let _res = a1.chain(a2).apply();
+}
+
+#[test]
+fn chained_released() {
+    let a = TVar::new(10u8);
+    let b = TVar::new(0u8);
+
+    let a1 = unchecked_prepare_atomically((&a,), |_tx, (a,)| {
+        *a -= 3;
+        Ok::<_, ()>(())
+    });
+    let b1 = unchecked_prepare_atomically((&b,), |_tx, (b,)| {
+        *b += 1;
+        Ok(())
+    });
+    let _ok = a1.chain(b1).apply().unwrap();
+
+    // the `TVar`s are no longer held after the commit
+    let (b0, _toks) = atomically((&b,), |tx, (b,)| {
+        *b += tx.read(&a);
+        Ok::<_, ()>(*b)
+    })
+    .unwrap();
+    assert_eq!(b0, 8);
+    // GOOD!
+}
@@ -0,0 +1,106 @@
+use onemut::{DynTransaction, OneMut};
+
+#[derive(Clone, Debug)]
+struct A(pub u8);
+
+#[derive(Clone, Debug)]
+struct B(pub Vec<u8>);
+
+#[test]
+fn runtime_sized() {
+    let mut aa = [A(0), A(1), A(2)];
+    let mut b = B(vec![]);
+
+    let mut tx = DynTransaction::new();
+    let mut a_keys = vec![];
+    for a in aa.iter_mut() {
+        let amut = OneMut::new(a);
+        a_keys.push(tx.push(amut.unchecked_prepare(|a: &mut A| {
+            a.0 += 10;
+            Ok::<_, ()>(())
+        })));
+    }
+    let bmut = OneMut::new(&mut b);
+    let b_key = tx.push(bmut.unchecked_prepare(|b: &mut B| {
+        b.0.push(7);
+        Ok(())
+    }));
+    assert_eq!(tx.len(), 4);
+    let (outputs, mut toks) = tx.apply().unwrap();
+
+    assert_eq!(outputs.len(), 4);
+    assert_eq!(toks.len(), 4);
+    assert!(toks.take(&b_key).is_some());
+    assert!(toks.take(&b_key).is_none());
+    // the members of the same `T` are told apart
+    assert!(toks.take(&a_keys[0]).is_some());
+    assert!(!toks.contains(&a_keys[0]));
+    assert!(toks.contains(&a_keys[1]));
+    assert_eq!(toks.len(), 2);
+
+    let aa: Vec<u8> = aa.iter().map(|a| a.0).collect();
+    assert_eq!(aa, vec![10, 11, 12]);
+    assert_eq!(b.0, vec![7]);
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut aa = [A(0), A(1), A(2)];
+    let mut b = B(vec![]);
+
+    let mut tx = DynTransaction::new();
+    let bmut = OneMut::new(&mut b);
+    let b_key = tx.push(bmut.unchecked_prepare(|b: &mut B| {
+        b.0.push(7);
+        Ok(())
+    }));
+    for a in aa.iter_mut() {
+        let amut = OneMut::new(a);
+        let _key = tx.push(amut.unchecked_prepare(|a: &mut A| {
+            if a.0 == 2 {
+                return Err(());
+            }
+            a.0 += 10;
+            Ok(())
+        }));
+    }
+    let (_err, mut toks) = tx.apply().unwrap_err();
+
+    // all of the tokens are given back
+    assert_eq!(toks.len(), 4);
+    let _b_tok = toks.take(&b_key).unwrap();
+
+    let aa: Vec<u8> = aa.iter().map(|a| a.0).collect();
+    assert_eq!(aa, vec![0, 1, 2]);
+    assert!(b.0.is_empty());
+    // GOOD!
+}
+
+#[test]
+fn foreign_key() {
+    let mut a1 = A(0);
+    let mut a2 = A(0);
+
+    let a1mut = OneMut::new(&mut a1);
+    let a2mut = OneMut::new(&mut a2);
+    let mut tx1 = DynTransaction::new();
+    let mut tx2 = DynTransaction::new();
+    let key1 = tx1.push(a1mut.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    }));
+    let _key2 = tx2.push(a2mut.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    }));
+    let (_outputs, mut toks2) = tx2.apply().unwrap();
+
+    // the key of another transaction doesn't take a token
+    assert!(toks2.take(&key1).is_none());
+    assert_eq!(toks2.len(), 1);
+
+    let (_outputs, _toks1) = tx1.apply().unwrap();
+    assert_eq!((a1.0, a2.0), (1, 1));
+    // GOOD!
+}
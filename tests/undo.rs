@@ -0,0 +1,81 @@
+use onemut::{ApplyReversible, OneMut};
+
+#[derive(Clone, Debug)]
+struct A(pub u8);
+
+#[derive(Clone, Debug)]
+struct B(pub u8);
+
+#[test]
+fn rollback() {
+    let mut a = A(0);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(a.0)
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 += 1;
+        Ok(b.0)
+    });
+    let (((a0, b0), undo), toks) = a_prep.chain(b_prep).commit_reversible().unwrap();
+    assert_eq!((a0, b0), (1, 1));
+    assert_eq!(undo.old().0 .0, 0);
+    assert_eq!(undo.old().1 .0, 0);
+
+    // a later (non-onemut) step failed, so the originals are restored
+    let _toks = undo.rollback(toks);
+
+    assert_eq!(a.0, 0);
+    assert_eq!(b.0, 0);
+    // GOOD!
+}
+
+#[test]
+fn finalize() {
+    let mut a = A(0);
+    let mut b = B(0);
+
+    let amut = OneMut::new(&mut a);
+    let bmut = OneMut::new(&mut b);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    });
+    let b_prep = bmut.unchecked_prepare(|b: &mut B| {
+        b.0 += 1;
+        Ok(())
+    });
+    let ((_, undo), _toks) = a_prep.chain(b_prep).commit_reversible().unwrap();
+
+    // the old values can be kept, but not restored anymore
+    let (old_a, old_b) = undo.finalize();
+    assert_eq!(old_a.0, 0);
+    assert_eq!(old_b.0, 0);
+
+    assert_eq!(a.0, 1);
+    assert_eq!(b.0, 1);
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let mut a = A(0);
+
+    let amut = OneMut::new(&mut a);
+    let a_prep = amut.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Err::<(), _>(())
+    });
+    let (_err, _tok) = match a_prep.commit_reversible() {
+        Ok(_) => panic!("the preparation should fail"),
+        Err(e) => e,
+    };
+
+    // the original state is kept intact
+    assert_eq!(a.0, 0);
+    // GOOD!
+}
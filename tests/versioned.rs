@@ -0,0 +1,88 @@
+use onemut::{Optimistic, RepeatedCellError, Versioned};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+struct A(pub u32);
+
+#[derive(Clone, Debug)]
+struct B(pub u32);
+
+#[test]
+fn concurrent_chain() {
+    let a = Arc::new(Versioned::new(A(0)));
+    let b = Arc::new(Versioned::new(B(0)));
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let a = Arc::clone(&a);
+            let b = Arc::clone(&b);
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    let a = a.unchecked_prepare(|a: &mut A| {
+                        a.0 += 1;
+                        Ok::<_, ()>(a.0)
+                    });
+                    let b = b.unchecked_prepare(|b: &mut B| {
+                        b.0 += 1;
+                        Ok(b.0)
+                    });
+                    let ((a0, b0), _toks) = a.chain(b).transact().unwrap().unwrap();
+
+                    // the snapshots were coherent
+                    assert_eq!(a0, b0);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(a.get().0, 400);
+    assert_eq!(b.get().0, 400);
+    assert_eq!(a.version(), 400);
+    // GOOD!
+}
+
+#[test]
+fn failed() {
+    let a = Versioned::new(A(0));
+    let b = Versioned::new(B(0));
+
+    let a_prep = a.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok(a.0)
+    });
+    let b_prep = b.unchecked_prepare(|b: &mut B| {
+        b.0 += 1;
+        Err::<u32, _>(())
+    });
+    let (_err, _toks) = a_prep.chain(b_prep).transact().unwrap().unwrap_err();
+
+    // the internal state is kept intact
+    assert_eq!(a.get().0, 0);
+    assert_eq!(b.get().0, 0);
+    assert_eq!(a.version(), 0);
+    // GOOD!
+}
+
+#[test]
+fn repeated() {
+    let a = Versioned::new(A(0));
+
+    let a1 = a.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok::<_, ()>(())
+    });
+    let a2 = a.unchecked_prepare(|a: &mut A| {
+        a.0 += 1;
+        Ok(())
+    });
+    // would otherwise always conflict with itself
+    let err = a1.chain(a2).transact().unwrap_err();
+    assert_eq!(err, RepeatedCellError { index: 1 });
+
+    // nothing was modified
+    assert_eq!(a.get().0, 0);
+    // GOOD!
+}